@@ -1,4 +1,3 @@
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BaseColor {
     Black = 0,
@@ -10,6 +9,12 @@ pub enum BaseColor {
     Cyan = 6,
     White = 7,
 }
+impl BaseColor {
+    pub const ALL: [BaseColor; 8] = [
+        BaseColor::Black, BaseColor::Red, BaseColor::Green, BaseColor::Yellow,
+        BaseColor::Blue, BaseColor::Magenta, BaseColor::Cyan, BaseColor::White,
+    ];
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Intensity { Normal, Bright }
@@ -38,8 +43,9 @@ pub struct RgbColor {
     pub b: u8,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Color {
+    #[default]
     Normal,
     Basic(BasicColor),
     Rgb(RgbColor)
@@ -58,11 +64,8 @@ impl Color {
         }
     }
 }
-impl Default for Color {
-    fn default() -> Self { Self::Normal }
-}
 
-// pub const RESET: u8 = 0;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Code {
     Reset = 0,
@@ -90,6 +93,19 @@ pub enum Code {
     SetBrightForegroundBase = 90,
     SetBrightBackgroundBase = 100,
 }
+impl Code {
+    const ALL: [Code; 24] = [
+        Code::Reset, Code::Bold, Code::Faint, Code::Italic, Code::Underline, Code::Blink, Code::Reverse,
+        Code::Conceal, Code::Strike, Code::NoBoldness, Code::NoItalic, Code::NoUnderline, Code::NoBlink,
+        Code::NoReverse, Code::NoConceal, Code::NoStrike, Code::SetForegroundBase, Code::SetForeground,
+        Code::DefaultForeground, Code::SetBackgroundBase, Code::SetBackground, Code::DefaultBackground,
+        Code::SetBrightForegroundBase, Code::SetBrightBackgroundBase,
+    ];
+    /// The code with exactly this SGR parameter, if it is modeled.
+    pub fn from_param(param: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|&code| code as u16 == param)
+    }
+}
 pub fn add_ansi_code(s: &mut String, params: impl IntoIterator<Item = u8>) {
     s.push_str("\u{1b}[");
     let mut params = params.into_iter();
@@ -100,5 +116,12 @@ pub fn add_ansi_code(s: &mut String, params: impl IntoIterator<Item = u8>) {
         s.push(';');
         s.push_str(&format!("{}", param));
     }
-    s.push('m');  
-}
\ No newline at end of file
+    s.push('m');
+}
+
+/// The OSC 8 sequence that starts a hyperlink to `url`, or ends the current one for `None`.
+pub fn add_hyperlink(s: &mut String, url: Option<&str>) {
+    s.push_str("\u{1b}]8;;");
+    s.push_str(url.unwrap_or_default());
+    s.push_str("\u{1b}\\");
+}
@@ -0,0 +1,34 @@
+//! Remapping of red and green, which people with deuteranopia or protanopia can't tell apart.
+//!
+//! Red becomes orange and green becomes blue, from the Okabe-Ito palette with truecolor
+//! and the closest basic colors (yellow and blue) without it.
+
+use crate::{codes::{BaseColor, BasicColor, Color, Intensity, RgbColor}, style::Style};
+
+impl Color {
+    /// The replacement for red and green, other colors are returned unchanged.
+    pub fn colorblind_safe(self, truecolor: bool) -> Color {
+        let Color::Basic(BasicColor { base, intensity }) = self else { return self };
+        let rgb = |r, g, b| Color::Rgb(RgbColor { r, g, b });
+        match (base, intensity, truecolor) {
+            (BaseColor::Red, Intensity::Normal, true) => rgb(230, 159, 0),
+            (BaseColor::Red, Intensity::Bright, true) => rgb(255, 190, 80),
+            (BaseColor::Green, Intensity::Normal, true) => rgb(0, 114, 178),
+            (BaseColor::Green, Intensity::Bright, true) => rgb(86, 180, 233),
+            (BaseColor::Red, _, false) => Color::Basic(BasicColor::new(BaseColor::Yellow, intensity)),
+            (BaseColor::Green, _, false) => Color::Basic(BasicColor::new(BaseColor::Blue, intensity)),
+            _ => self,
+        }
+    }
+}
+
+impl Style {
+    /// Replaces red and green in the foreground and background color, see [`Color::colorblind_safe`].
+    pub fn colorblind_safe(self, truecolor: bool) -> Style {
+        Style {
+            fg_color: self.fg_color.colorblind_safe(truecolor),
+            bg_color: self.bg_color.colorblind_safe(truecolor),
+            ..self
+        }
+    }
+}
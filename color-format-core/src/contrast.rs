@@ -0,0 +1,65 @@
+//! WCAG 2 contrast ratios between foreground and background colors.
+//!
+//! Basic colors depend on the terminal, they are measured with the default xterm palette.
+
+use crate::{codes::{BaseColor, BasicColor, Color, Intensity, RgbColor}, style::Style};
+
+impl RgbColor {
+    /// The relative luminance as defined by WCAG, from 0 for black to 1 for white.
+    pub fn relative_luminance(self) -> f64 {
+        let channel = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        0.2126 * channel(self.r) + 0.7152 * channel(self.g) + 0.0722 * channel(self.b)
+    }
+}
+
+impl BasicColor {
+    /// The color in the default xterm palette.
+    pub fn reference_rgb(self) -> RgbColor {
+        let (r, g, b) = match (self.intensity, self.base) {
+            (Intensity::Normal, BaseColor::Black) => (0, 0, 0),
+            (Intensity::Normal, BaseColor::Red) => (205, 0, 0),
+            (Intensity::Normal, BaseColor::Green) => (0, 205, 0),
+            (Intensity::Normal, BaseColor::Yellow) => (205, 205, 0),
+            (Intensity::Normal, BaseColor::Blue) => (0, 0, 238),
+            (Intensity::Normal, BaseColor::Magenta) => (205, 0, 205),
+            (Intensity::Normal, BaseColor::Cyan) => (0, 205, 205),
+            (Intensity::Normal, BaseColor::White) => (229, 229, 229),
+            (Intensity::Bright, BaseColor::Black) => (127, 127, 127),
+            (Intensity::Bright, BaseColor::Red) => (255, 0, 0),
+            (Intensity::Bright, BaseColor::Green) => (0, 255, 0),
+            (Intensity::Bright, BaseColor::Yellow) => (255, 255, 0),
+            (Intensity::Bright, BaseColor::Blue) => (92, 92, 255),
+            (Intensity::Bright, BaseColor::Magenta) => (255, 0, 255),
+            (Intensity::Bright, BaseColor::Cyan) => (0, 255, 255),
+            (Intensity::Bright, BaseColor::White) => (255, 255, 255),
+        };
+        RgbColor { r, g, b }
+    }
+}
+
+impl Color {
+    /// The rgb value used for contrast checks, `None` for the terminal's default color.
+    pub fn reference_rgb(self) -> Option<RgbColor> {
+        match self {
+            Color::Normal => None,
+            Color::Basic(basic) => Some(basic.reference_rgb()),
+            Color::Rgb(rgb) => Some(rgb),
+        }
+    }
+}
+
+/// The contrast ratio between two colors, from 1 for equal colors to 21 for black on white.
+pub fn contrast_ratio(a: RgbColor, b: RgbColor) -> f64 {
+    let (a, b) = (a.relative_luminance(), b.relative_luminance());
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+impl Style {
+    /// The contrast ratio of the text if both the foreground and the background color are set.
+    pub fn contrast_ratio(&self) -> Option<f64> {
+        Some(contrast_ratio(self.fg_color.reference_rgb()?, self.bg_color.reference_rgb()?))
+    }
+}
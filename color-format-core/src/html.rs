@@ -0,0 +1,173 @@
+//! Rendering styled text as HTML `<span>` elements, shared by `cformat_html!` and the runtime renderer.
+
+use crate::{
+    codes::{BasicColor, Color, Intensity},
+    palette::Palette,
+    style::{Boldness, Style},
+};
+
+/// How styles are written in HTML.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlOptions {
+    /// Use CSS classes starting with this prefix instead of inline styles, see [`HtmlOptions::stylesheet`].
+    ///
+    /// Rgb colors are always written inline.
+    pub class_prefix: Option<String>,
+    pub palette: Palette,
+}
+
+/// The flags of a style with their CSS class and its declaration in the stylesheet.
+const FLAGS: [(&str, &str); 6] = [
+    ("italic", "font-style: italic"),
+    ("underline", "text-decoration: underline"),
+    ("blink", "text-decoration: blink"),
+    ("reverse", "filter: invert(1)"),
+    ("conceal", "visibility: hidden"),
+    ("strike", "text-decoration: line-through"),
+];
+
+fn flags(style: &Style) -> [bool; 6] {
+    [style.italic, style.underline, style.blink, style.reverse, style.conceal, style.strike]
+}
+
+impl HtmlOptions {
+    /// Writes the tags between text with the style and link `from` and text with `to`,
+    /// from outside of all tags for the default style and no link.
+    ///
+    /// Links that are not [safe](is_safe_link) are left out and their text is written plain.
+    pub fn transition(&self, from: (&Style, Option<&str>), to: (&Style, Option<&str>), out: &mut String) {
+        if from == to {
+            return;
+        }
+        if !from.0.is_default() {
+            out.push_str("</span>");
+        }
+        let (from_link, to_link) = (from.1.filter(|url| is_safe_link(url)), to.1.filter(|url| is_safe_link(url)));
+        if from_link != to_link {
+            if from_link.is_some() {
+                out.push_str("</a>");
+            }
+            if let Some(url) = to_link {
+                out.push_str("<a href=\"");
+                escape_html(url, out);
+                out.push_str("\">");
+            }
+        }
+        if !to.0.is_default() {
+            self.start_tag(to.0, out);
+        }
+    }
+
+    fn start_tag(&self, style: &Style, out: &mut String) {
+        let mut classes = Vec::new();
+        let mut declarations = Vec::new();
+        let (mut fg, mut bg) = (style.fg_color, style.bg_color);
+        match &self.class_prefix {
+            Some(prefix) => {
+                match style.boldness {
+                    Boldness::Normal => (),
+                    Boldness::Bold => classes.push(format!("{}bold", prefix)),
+                    Boldness::Faint => classes.push(format!("{}faint", prefix)),
+                }
+                for ((class, _), set) in FLAGS.iter().zip(flags(style)) {
+                    if set {
+                        classes.push(format!("{}{}", prefix, class));
+                    }
+                }
+                for (color, name) in [(&mut fg, "fg"), (&mut bg, "bg")] {
+                    if let Color::Basic(basic) = *color {
+                        classes.push(format!("{}{}-{}", prefix, name, color_name(basic)));
+                        *color = Color::Normal;
+                    }
+                }
+            }
+            None => {
+                match style.boldness {
+                    Boldness::Normal => (),
+                    Boldness::Bold => declarations.push("font-weight:bold".to_owned()),
+                    Boldness::Faint => declarations.push("opacity:0.6".to_owned()),
+                }
+                let decorations = [
+                    (style.underline, "underline"), (style.strike, "line-through"), (style.blink, "blink"),
+                ].into_iter()
+                    .filter_map(|(set, decoration)| set.then_some(decoration))
+                    .collect::<Vec<_>>();
+                if !decorations.is_empty() {
+                    declarations.push(format!("text-decoration:{}", decorations.join(" ")));
+                }
+                if style.italic {
+                    declarations.push("font-style:italic".to_owned());
+                }
+                if style.conceal {
+                    declarations.push("visibility:hidden".to_owned());
+                }
+                if style.reverse {
+                    let palette = &self.palette;
+                    let (fg_rgb, bg_rgb) = (palette.rgb(fg).unwrap_or(palette.foreground), palette.rgb(bg));
+                    fg = Color::Rgb(bg_rgb.unwrap_or(palette.background));
+                    bg = Color::Rgb(fg_rgb);
+                }
+            }
+        }
+        for (color, property) in [(fg, "color"), (bg, "background-color")] {
+            if let Some(rgb) = self.palette.rgb(color) {
+                declarations.push(format!("{}:{}", property, rgb.to_hex()));
+            }
+        }
+        out.push_str("<span");
+        if !classes.is_empty() {
+            out.push_str(&format!(" class=\"{}\"", classes.join(" ")));
+        }
+        if !declarations.is_empty() {
+            out.push_str(&format!(" style=\"{}\"", declarations.join(";")));
+        }
+        out.push('>');
+    }
+
+    /// The CSS for the classes used with a [`HtmlOptions::class_prefix`], empty without one.
+    pub fn stylesheet(&self) -> String {
+        let Some(prefix) = &self.class_prefix else { return String::new() };
+        let mut css = format!(".{0}bold {{ font-weight: bold; }}\n.{0}faint {{ opacity: 0.6; }}\n", prefix);
+        for (class, declaration) in FLAGS {
+            css.push_str(&format!(".{}{} {{ {}; }}\n", prefix, class, declaration));
+        }
+        for (color, rgb) in self.palette.iter() {
+            let name = color_name(color);
+            css.push_str(&format!(".{}fg-{} {{ color: {}; }}\n", prefix, name, rgb.to_hex()));
+            css.push_str(&format!(".{}bg-{} {{ background-color: {}; }}\n", prefix, name, rgb.to_hex()));
+        }
+        css
+    }
+}
+
+/// The long name of a color in tags, like `bright-red`.
+fn color_name(color: BasicColor) -> String {
+    let name = color.base.names().0;
+    match color.intensity {
+        Intensity::Normal => name.to_owned(),
+        Intensity::Bright => format!("bright-{}", name),
+    }
+}
+
+/// Whether a link target may be written to an `href`, only `http`, `https`, `mailto` and `file` URLs are.
+///
+/// Links can come from untrusted output parsed with `StyledText::from_ansi`, so schemes like `javascript:` must not
+/// end up clickable.
+pub fn is_safe_link(url: &str) -> bool {
+    let Some((scheme, _)) = url.split_once(':') else { return false };
+    ["http", "https", "mailto", "file"].iter().any(|safe| scheme.eq_ignore_ascii_case(safe))
+}
+
+/// Escapes `&`, `<`, `>`, `"` and `'`.
+pub fn escape_html(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
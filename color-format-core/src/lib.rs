@@ -0,0 +1,29 @@
+//! The tag grammar and style model of `color-format`.
+//!
+//! The macros parse their format strings with this crate at compile time and the runtime
+//! [`Template`](https://docs.rs/color-format) uses it as well, so both always agree.
+//! Tools like linters and editors can use it to parse templates exactly like the macros do.
+
+pub mod codes;
+pub mod colorblind;
+pub mod contrast;
+pub mod html;
+pub mod palette;
+pub mod parse;
+pub mod roff;
+pub mod sgr;
+pub mod style;
+pub mod theme;
+
+pub use codes::{BaseColor, BasicColor, Color, Intensity, RgbColor};
+pub use contrast::contrast_ratio;
+pub use html::HtmlOptions;
+pub use palette::Palette;
+pub use parse::{
+    parse_commands, parse_commands_with, Aliases, Attributes, Cmd, StringParser, StringPart, TemplateError,
+    TemplateErrorKind, SECRET_MASK,
+};
+pub use roff::RoffOptions;
+pub use sgr::SgrError;
+pub use style::{Boldness, Style};
+pub use theme::{Fallback, Semantic, Theme};
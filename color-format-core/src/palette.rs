@@ -0,0 +1,60 @@
+//! The rgb values that basic colors are shown in by backends other than terminals.
+
+use crate::codes::{BaseColor, BasicColor, Color, Intensity, RgbColor};
+
+/// The 16 basic colors in the order of their ANSI indices, bright colors last,
+/// and the default colors of the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [RgbColor; 16],
+    pub foreground: RgbColor,
+    pub background: RgbColor,
+}
+impl Palette {
+    /// The default xterm colors with light text on black.
+    pub fn xterm() -> Self {
+        let colors = std::array::from_fn(|i| basic(i).reference_rgb());
+        Self { colors, foreground: RgbColor { r: 229, g: 229, b: 229 }, background: RgbColor { r: 0, g: 0, b: 0 } }
+    }
+
+    pub fn get(&self, color: BasicColor) -> RgbColor {
+        let bright = if color.intensity == Intensity::Bright { 8 } else { 0 };
+        self.colors[color.base as usize + bright]
+    }
+
+    /// The basic colors with their rgb values, in the order of [`Palette::colors`].
+    pub fn iter(&self) -> impl Iterator<Item = (BasicColor, RgbColor)> + '_ {
+        self.colors.iter().enumerate().map(|(i, &rgb)| (basic(i), rgb))
+    }
+
+    /// The rgb value of a color, `None` for the default color.
+    pub fn rgb(&self, color: Color) -> Option<RgbColor> {
+        match color {
+            Color::Normal => None,
+            Color::Basic(basic) => Some(self.get(basic)),
+            Color::Rgb(rgb) => Some(rgb),
+        }
+    }
+}
+fn basic(index: usize) -> BasicColor {
+    let intensity = if index < 8 { Intensity::Normal } else { Intensity::Bright };
+    BasicColor::new(BaseColor::ALL[index % 8], intensity)
+}
+
+impl Default for Palette {
+    fn default() -> Self { Self::xterm() }
+}
+
+impl RgbColor {
+    /// Parses `#rrggbb`.
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let hex = s.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii())?;
+        let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Self { r: component(0)?, g: component(2)?, b: component(4)? })
+    }
+
+    /// Writes the color as `#rrggbb`.
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
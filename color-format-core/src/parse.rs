@@ -0,0 +1,461 @@
+use std::{collections::HashMap, fmt, iter::Peekable, ops::Range, str::CharIndices};
+
+use crate::{
+    codes::{BaseColor, BasicColor, Color, Intensity, RgbColor}, style::{Boldness, Style},
+    theme::{default_theme, Fallback, Semantic, Theme},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmd {
+    Color { color: Color, background: bool },
+    Bold,
+    Faint,
+    Italic,
+    Underline,
+    Blink,
+    Reverse,
+    Conceal,
+    Strike,
+    /// A semantic tag like `error` that is styled by a [`Theme`].
+    Semantic(Semantic),
+    /// `#secret<...>`, the content is replaced by [`SECRET_MASK`] when colors are off.
+    Secret,
+}
+
+/// What is written instead of the content of a `#secret<...>` tag when colors are off.
+pub const SECRET_MASK: &str = "********";
+impl Cmd {
+    /// Applies the command, semantic commands use the default theme.
+    pub fn apply(self, style: &mut Style) {
+        // the default theme is parsed from tags, which applies commands that are not semantic
+        match self {
+            Cmd::Semantic(_) => self.apply_themed(style, default_theme()),
+            cmd => cmd.apply_to(style, |_| unreachable!()),
+        }
+    }
+    pub fn apply_themed(self, style: &mut Style, theme: &Theme) {
+        self.apply_to(style, |semantic| theme.get(semantic));
+    }
+    fn apply_to(self, style: &mut Style, semantic_style: impl FnOnce(Semantic) -> Style) {
+        match self {
+            Cmd::Color { color, background: false } => style.fg_color = color,
+            Cmd::Color { color, background: true } => style.bg_color = color,
+            Cmd::Bold => style.boldness = Boldness::Bold,
+            Cmd::Faint => style.boldness = Boldness::Faint,
+            Cmd::Italic => style.italic = true,
+            Cmd::Underline => style.underline = true,
+            Cmd::Blink => style.blink = true,
+            Cmd::Reverse => style.reverse = true,
+            Cmd::Conceal => style.conceal = true,
+            Cmd::Strike => style.strike = true,
+            Cmd::Semantic(semantic) => style.overlay(&semantic_style(semantic)),
+            Cmd::Secret => (),
+        }
+    }
+}
+
+/// An error in a template, `span` is the range of bytes it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub kind: TemplateErrorKind,
+    pub span: Range<usize>,
+}
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.span.start)
+    }
+}
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateErrorKind {
+    /// A `#` at the end of the template.
+    MissingTag,
+    /// A tag that isn't followed by `<`.
+    MissingOpen,
+    UnknownTag(String),
+    /// `bright-`/`!` used on something that is not a basic color.
+    InvalidBright(String),
+    /// `bg:`/`_` used on something that is not a color.
+    InvalidBackground(String),
+    InvalidRgb(&'static str),
+    /// A `>` without a matching tag.
+    UnmatchedClose,
+    /// A tag that is never closed with `>`.
+    Unclosed,
+    /// A `{` without a matching `}`.
+    UnclosedPlaceholder,
+    /// A single `}` that is not part of a placeholder.
+    UnmatchedBrace,
+    /// A placeholder with an invalid name.
+    InvalidPlaceholder(String),
+    /// An alias name that is not an identifier or is a built-in tag.
+    InvalidAlias(String),
+    /// A tag attribute other than `fallback="..."`, `fallback-end="..."` and `link="..."`.
+    InvalidAttribute(String),
+    /// Tag attributes that are not closed with `)`.
+    UnclosedAttributes,
+}
+impl fmt::Display for TemplateErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTag => f.write_str("color tag expected"),
+            Self::MissingOpen => f.write_str("'<' expected after color tag"),
+            Self::UnknownTag(tag) => write!(f, "unknown tag '{}'", tag),
+            Self::InvalidBright(tag) => write!(f,
+                "invalid tag, 'bright-'/'!' can only be applied to basic colors and '{}' is not one of them", tag
+            ),
+            Self::InvalidBackground(tag) => write!(f,
+                "invalid tag, 'bg:'/'_' can only be applied to colors and '{}' is not one of them", tag
+            ),
+            Self::InvalidRgb(msg) => f.write_str(msg),
+            Self::UnmatchedClose => f.write_str("'>' without a matching tag, use '#>' for a literal '>'"),
+            Self::Unclosed => f.write_str("tag is never closed with '>'"),
+            Self::UnclosedPlaceholder => f.write_str("missing '}' after placeholder"),
+            Self::UnmatchedBrace => f.write_str("unmatched '}', use '}}' for a literal '}'"),
+            Self::InvalidPlaceholder(name) => write!(f, "invalid placeholder '{{{}}}'", name),
+            Self::InvalidAlias(name) => write!(f, "invalid alias name '{}'", name),
+            Self::InvalidAttribute(name) => write!(f,
+                "invalid tag attribute '{}', expected fallback=\"...\", fallback-end=\"...\" or link=\"...\"", name
+            ),
+            Self::UnclosedAttributes => f.write_str("tag attributes are not closed with ')'"),
+        }
+    }
+}
+
+/// Parses the `;`-separated commands of a tag like `"bold;bright-red;bg:rgb(0,0,80)"`.
+///
+/// `offset` is the position of `s` in the template and is added to the spans of errors.
+pub fn parse_commands(s: &str, offset: usize) -> Result<Vec<Cmd>, TemplateError> {
+    CmdParser::parse_commands(s, offset, None)
+}
+
+/// Like [`parse_commands`] but also resolves the names defined in `aliases`.
+pub fn parse_commands_with(s: &str, offset: usize, aliases: &Aliases) -> Result<Vec<Cmd>, TemplateError> {
+    CmdParser::parse_commands(s, offset, Some(aliases))
+}
+
+/// Custom tag names that stand for a list of built-in commands, like `error` for `bright-red;bold`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Aliases {
+    map: HashMap<String, Vec<Cmd>>,
+}
+impl Aliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Defines `name` as the commands in `commands`, which may only use built-in tags.
+    ///
+    /// Fails if `commands` is invalid or `name` is not a plain identifier like `error` or `file-path`
+    /// or is already a built-in tag. Aliases may replace the semantic tags like `error`.
+    pub fn insert(&mut self, name: &str, commands: &str) -> Result<(), TemplateError> {
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name || (parse_commands(name, 0).is_ok() && Semantic::from_name(name).is_none()) {
+            return Err(TemplateError {
+                kind: TemplateErrorKind::InvalidAlias(name.to_owned()),
+                span: 0..name.len(),
+            });
+        }
+        let cmds = parse_commands(commands, 0)?;
+        self.map.insert(name.to_owned(), cmds);
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<&[Cmd]> {
+        self.map.get(name).map(Vec::as_slice)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+struct CmdParser<'a> {
+    s: &'a str,
+    offset: usize,
+}
+impl<'a> CmdParser<'a> {
+    fn parse_commands(s: &'a str, offset: usize, aliases: Option<&Aliases>) -> Result<Vec<Cmd>, TemplateError> {
+        let mut start = offset;
+        let mut cmds = Vec::new();
+        for cmd in s.split(';') {
+            let trimmed = cmd.trim_start();
+            let cmd_offset = start + cmd.len() - trimmed.len();
+            start += cmd.len() + 1;
+            let trimmed = trimmed.trim_end();
+            match aliases.and_then(|aliases| aliases.get(trimmed)) {
+                Some(alias) => cmds.extend_from_slice(alias),
+                None => cmds.push(Self { s: trimmed, offset: cmd_offset }.parse_command()?),
+            }
+        }
+        Ok(cmds)
+    }
+
+    fn skip_if(&mut self, prefix: &str) -> bool {
+        if self.s.starts_with(prefix) {
+            self.s = &self.s[prefix.len() ..];
+            true
+        } else { false }
+    }
+    fn skip_if_any<'s>(&mut self, prefixes: impl IntoIterator<Item = &'s str>) -> bool {
+        prefixes.into_iter().any(|s| self.skip_if(s))
+    }
+    fn get_base_color(s: &str) -> Option<BaseColor> {
+        Some(match s {
+            "k" | "black" => BaseColor::Black,
+            "r" | "red" => BaseColor::Red,
+            "g" | "green" => BaseColor::Green,
+            "y" | "yellow" => BaseColor::Yellow,
+            "b" | "blue" => BaseColor::Blue,
+            "m" | "magenta" => BaseColor::Magenta,
+            "c" | "cyan" => BaseColor::Cyan,
+            "w" | "white" => BaseColor::White,
+            _ => return None
+        })
+    }
+    fn error(&self, kind: TemplateErrorKind, len: usize) -> TemplateError {
+        TemplateError { kind, span: self.offset .. self.offset + len }
+    }
+
+    fn parse_command(&mut self) -> Result<Cmd, TemplateError> {
+        let full_len = self.s.len();
+        let background = self.skip_if_any(["_", "bg:"]);
+        let intensity = if self.skip_if("bright-") { Intensity::Bright }
+        else if self.s.ends_with('!') {
+            self.s = &self.s[..self.s.len() - 1];
+            Intensity::Bright
+        } else { Intensity::Normal };
+        if let Some(base) = Self::get_base_color(self.s) {
+            return Ok(Cmd::Color {
+                color: Color::Basic(BasicColor::new(base, intensity)),
+                background
+            });
+        }
+        if intensity == Intensity::Bright {
+            return Err(self.error(TemplateErrorKind::InvalidBright(self.s.to_owned()), full_len));
+        }
+        if self.skip_if("rgb(") {
+            let rgb_error = |msg| self.error(TemplateErrorKind::InvalidRgb(msg), full_len);
+            let end = self.s.find(')').ok_or_else(|| rgb_error("missing ')' in rgb tag"))?;
+            let mut split = self.s[..end].split(',');
+            let mut component = || split.next()
+                .ok_or_else(|| rgb_error("not enough arguments in rgb tag"))?
+                .trim()
+                .parse()
+                .map_err(|_| rgb_error("invalid component in rgb tag, value from 0 to 255 expected"));
+            let (r, g, b) = (component()?, component()?, component()?);
+            if split.next().is_some() || end + 1 != self.s.len() {
+                return Err(rgb_error("too many components in an rgb tag, 3 components were expected"));
+            }
+            return Ok(Cmd::Color { color: Color::Rgb(RgbColor { r, g, b }), background });
+        }
+        if background {
+            return Err(self.error(TemplateErrorKind::InvalidBackground(self.s.to_owned()), full_len));
+        }
+        if let Some(semantic) = Semantic::from_name(self.s) {
+            return Ok(Cmd::Semantic(semantic));
+        }
+        Ok(match self.s {
+            "bold" | "s" => Cmd::Bold,
+            "faint" | "f" => Cmd::Faint,
+            "italic" | "i" => Cmd::Italic,
+            "underline" | "u" => Cmd::Underline,
+            "blink" => Cmd::Blink,
+            "reverse" => Cmd::Reverse,
+            "conceal" => Cmd::Conceal,
+            "strike" => Cmd::Strike,
+            "secret" => Cmd::Secret,
+            other => return Err(self.error(TemplateErrorKind::UnknownTag(other.to_owned()), full_len)),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StringPart<'a> {
+    /// Literal text, braces are still escaped as `{{` and `}}`.
+    String(&'a str),
+    /// A placeholder including its braces, like `{name:>4}`.
+    Placeholder(&'a str),
+    StartCmd(Vec<Cmd>),
+    /// The attributes declared with `#tag(fallback="...")<`, directly follow the `StartCmd` of the tag.
+    Attributes(Attributes<'a>),
+    EndCmd,
+}
+
+/// The attributes in parentheses after the commands of a tag, like `#u(link='https://example.com')<...>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attributes<'a> {
+    /// From `fallback="..."` and `fallback-end="..."`, replaces the fallback of semantic tags.
+    pub fallback: Option<Fallback<'a>>,
+    /// A hyperlink around the content, from `link="..."`.
+    pub link: Option<&'a str>,
+}
+
+/// Splits a template into its parts, each together with its byte offset.
+pub struct StringParser<'a> {
+    s: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    depth: Vec<usize>,
+    aliases: Option<&'a Aliases>,
+    pending: Option<(usize, StringPart<'a>)>,
+}
+impl<'a> StringParser<'a> {
+    pub fn new(s: &'a str) -> Self {
+        Self { s, chars: s.char_indices().peekable(), depth: Vec::new(), aliases: None, pending: None }
+    }
+    /// Also resolves the tags defined in `aliases`.
+    pub fn with_aliases(s: &'a str, aliases: &'a Aliases) -> Self {
+        Self { aliases: Some(aliases), ..Self::new(s) }
+    }
+    fn skip_whitespace(&mut self) {
+        while let Some((_, c)) = self.chars.peek() {
+            if !c.is_whitespace() { break }
+            self.chars.next();
+        }
+    }
+    /// Parses `name="value", ...)` after the `(` that starts the attributes of a tag.
+    fn attributes(&mut self, open: usize) -> Result<Attributes<'a>, TemplateError> {
+        let mut attributes = Attributes::default();
+        loop {
+            self.skip_whitespace();
+            let Some(&(name_start, _)) = self.chars.peek() else { break };
+            let mut name_end = name_start;
+            while let Some(&(i, c)) = self.chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '-') { break }
+                name_end = i + 1;
+                self.chars.next();
+            }
+            let name = &self.s[name_start..name_end];
+            self.skip_whitespace();
+            let invalid = |end| TemplateError {
+                kind: TemplateErrorKind::InvalidAttribute(name.to_owned()),
+                span: name_start..end,
+            };
+            if !matches!(self.chars.next(), Some((_, '='))) {
+                return Err(invalid(name_end));
+            }
+            self.skip_whitespace();
+            let Some((quote_start, quote @ ('"' | '\''))) = self.chars.next() else { return Err(invalid(name_end)) };
+            let value_end = loop {
+                match self.chars.next() {
+                    Some((end, c)) if c == quote => break end,
+                    Some(_) => (),
+                    None => return Err(TemplateError {
+                        kind: TemplateErrorKind::UnclosedAttributes,
+                        span: open..self.s.len(),
+                    }),
+                }
+            };
+            let value = &self.s[quote_start + 1..value_end];
+            match name {
+                "fallback" => attributes.fallback.get_or_insert_with(Fallback::default).before = value,
+                "fallback-end" => attributes.fallback.get_or_insert_with(Fallback::default).after = value,
+                "link" => attributes.link = Some(value),
+                _ => return Err(invalid(value_end + 1)),
+            }
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => (),
+                Some((_, ')')) => return Ok(attributes),
+                Some((i, _)) => return Err(TemplateError { kind: TemplateErrorKind::UnclosedAttributes, span: open..i }),
+                None => break,
+            }
+        }
+        Err(TemplateError { kind: TemplateErrorKind::UnclosedAttributes, span: open..self.s.len() })
+    }
+    fn error(kind: TemplateErrorKind, span: Range<usize>) -> Option<Result<(usize, StringPart<'a>), TemplateError>> {
+        Some(Err(TemplateError { kind, span }))
+    }
+}
+impl<'a> Iterator for StringParser<'a> {
+    type Item = Result<(usize, StringPart<'a>), TemplateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(pending) = self.pending.take() {
+            return Some(Ok(pending));
+        }
+        match self.chars.next() {
+            Some((start, '#')) => {
+                if let Some(&(i, c @ ('#' | '<' | '>'))) = self.chars.peek() {
+                    self.chars.next();
+                    return Some(Ok((start, StringPart::String(&self.s[i..i + c.len_utf8()]))));
+                }
+                self.skip_whitespace();
+                let Some(&(tag_start, _)) = self.chars.peek() else {
+                    return Self::error(TemplateErrorKind::MissingTag, start..start + 1);
+                };
+                let mut attributes = None;
+                let tag_end = loop {
+                    match self.chars.next() {
+                        Some((end, '<')) => break end,
+                        // attributes start with a name, unlike the arguments of `rgb(...)`
+                        Some((end, '(')) if self.chars.peek().is_some_and(|(_, c)| c.is_alphabetic()) => {
+                            match self.attributes(end) {
+                                Ok(parsed) => attributes = Some((end, parsed)),
+                                Err(e) => return Some(Err(e)),
+                            }
+                            self.skip_whitespace();
+                            if !matches!(self.chars.next(), Some((_, '<'))) {
+                                return Self::error(TemplateErrorKind::MissingOpen, start..end);
+                            }
+                            break end
+                        }
+                        Some((end, c)) if c.is_whitespace() => {
+                            self.skip_whitespace();
+                            if !matches!(self.chars.next(), Some((_, '<'))) {
+                                return Self::error(TemplateErrorKind::MissingOpen, start..end);
+                            }
+                            break end
+                        }
+                        Some(_) => (),
+                        None => return Self::error(TemplateErrorKind::MissingOpen, start..self.s.len()),
+                    }
+                };
+                self.depth.push(start);
+                // a tag can consist of only attributes, like `#(link='...')<...>`
+                let cmds = match &self.s[tag_start..tag_end] {
+                    "" if attributes.is_some() => Vec::new(),
+                    tag => match CmdParser::parse_commands(tag, tag_start, self.aliases) {
+                        Ok(cmds) => cmds,
+                        Err(e) => return Some(Err(e)),
+                    },
+                };
+                self.pending = attributes.map(|(offset, attributes)| (offset, StringPart::Attributes(attributes)));
+                Some(Ok((start, StringPart::StartCmd(cmds))))
+            }
+            Some((start, '{')) => {
+                if matches!(self.chars.peek(), Some((_, '{'))) {
+                    self.chars.next();
+                    return Some(Ok((start, StringPart::String("{{"))));
+                }
+                loop {
+                    match self.chars.next() {
+                        Some((end, '}')) => break Some(Ok((start, StringPart::Placeholder(&self.s[start..=end])))),
+                        Some(_) => (),
+                        None => break Self::error(TemplateErrorKind::UnclosedPlaceholder, start..self.s.len()),
+                    }
+                }
+            }
+            Some((start, '>')) => {
+                if self.depth.pop().is_none() {
+                    return Self::error(TemplateErrorKind::UnmatchedClose, start..start + 1);
+                }
+                Some(Ok((start, StringPart::EndCmd)))
+            }
+            Some((start, _)) => {
+                let end = loop {
+                    match self.chars.peek() {
+                        Some(&(end, '#' | '>' | '{')) => break end,
+                        None => break self.s.len(),
+                        _ => { self.chars.next(); }
+                    }
+                };
+                Some(Ok((start, StringPart::String(&self.s[start..end]))))
+            }
+            None => {
+                let start = self.depth.pop()?;
+                self.depth.clear();
+                Self::error(TemplateErrorKind::Unclosed, start..start + 1)
+            }
+        }
+    }
+}
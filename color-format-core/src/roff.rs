@@ -0,0 +1,71 @@
+//! Rendering styled text as roff for man pages, shared by `cformat_roff!` and the runtime renderer.
+
+use crate::{
+    codes::{BaseColor, Color},
+    style::{Boldness, Style},
+};
+
+/// How styles are written in roff.
+///
+/// Bold text uses the bold font, italic and underlined text the italic font. Other attributes and links are dropped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoffOptions {
+    /// Write basic foreground colors as groff colors like `\m[red]`, other colors are always dropped.
+    pub colors: bool,
+}
+
+impl RoffOptions {
+    /// Writes the escapes between text with the style `from` and text with `to`, starting with the default style.
+    pub fn transition(&self, from: &Style, to: &Style, out: &mut String) {
+        if font(from) != font(to) {
+            out.push_str(font(to));
+        }
+        if self.colors && color(from.fg_color) != color(to.fg_color) {
+            out.push_str(&format!("\\m[{}]", color(to.fg_color).unwrap_or_default()));
+        }
+    }
+}
+
+fn font(style: &Style) -> &'static str {
+    match (style.boldness == Boldness::Bold, style.italic || style.underline) {
+        (false, false) => "\\fR",
+        (true, false) => "\\fB",
+        (false, true) => "\\fI",
+        (true, true) => "\\f(BI",
+    }
+}
+
+fn color(color: Color) -> Option<&'static str> {
+    match color {
+        Color::Basic(basic) => Some(match basic.base {
+            BaseColor::Black => "black",
+            BaseColor::Red => "red",
+            BaseColor::Green => "green",
+            BaseColor::Yellow => "yellow",
+            BaseColor::Blue => "blue",
+            BaseColor::Magenta => "magenta",
+            BaseColor::Cyan => "cyan",
+            BaseColor::White => "white",
+        }),
+        Color::Normal | Color::Rgb(_) => None,
+    }
+}
+
+/// Escapes backslashes and hyphens, and a `.` or `'` at the start of a line that would start a request.
+///
+/// `line_start` tells whether `s` starts at the beginning of a line.
+pub fn escape_roff(s: &str, line_start: bool, out: &mut String) {
+    let mut line_start = line_start;
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\e"),
+            '-' => out.push_str("\\-"),
+            '.' | '\'' if line_start => {
+                out.push_str("\\&");
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+        line_start = c == '\n';
+    }
+}
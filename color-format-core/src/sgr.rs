@@ -0,0 +1,142 @@
+//! Parsing of SGR ("select graphic rendition") parameter lists like `01;34` into a [`Style`].
+
+use std::fmt;
+
+use crate::{codes::{BaseColor, BasicColor, Code, Color, Intensity, RgbColor}, style::{Boldness, Style}};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SgrError {
+    /// A parameter that is not a number.
+    InvalidParam(String),
+    /// A valid SGR code that has no equivalent in [`Code`].
+    Unsupported(u16),
+    /// An extended color (`38`/`48`) with a mode other than `5` (256 colors) or `2` (rgb).
+    InvalidColorMode(u16),
+    /// An extended color with missing or out of range arguments.
+    InvalidColorArgs,
+}
+impl fmt::Display for SgrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidParam(param) => write!(f, "invalid SGR parameter '{}'", param),
+            Self::Unsupported(code) => write!(f, "SGR code {} is not supported", code),
+            Self::InvalidColorMode(mode) => write!(f,
+                "unknown extended color mode {}, only 5 (256 colors) and 2 (rgb) are supported", mode
+            ),
+            Self::InvalidColorArgs => f.write_str("missing or invalid arguments for an extended color"),
+        }
+    }
+}
+impl std::error::Error for SgrError {}
+
+impl Style {
+    /// Parses a `;`-separated SGR parameter list, starting from the default style.
+    pub fn from_sgr(params: &str) -> Result<Self, SgrError> {
+        let mut style = Style::default();
+        style.apply_sgr(params)?;
+        Ok(style)
+    }
+
+    /// Applies a `;`-separated SGR parameter list like `01;34` on top of this style.
+    ///
+    /// An empty list resets the style, like `ESC[m` does.
+    pub fn apply_sgr(&mut self, params: &str) -> Result<(), SgrError> {
+        let params = params.split(';')
+            .map(|param| match param.trim() {
+                "" => Ok(0),
+                param => param.parse::<u16>().map_err(|_| SgrError::InvalidParam(param.to_owned())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.apply_sgr_codes(&params)
+    }
+
+    /// Like [`Style::apply_sgr`] but with already separated numeric parameters.
+    pub fn apply_sgr_codes(&mut self, params: &[u16]) -> Result<(), SgrError> {
+        self.apply_codes(params, false)
+    }
+
+    /// Like [`Style::apply_sgr_codes`] but skips unsupported codes, as terminals do.
+    ///
+    /// An invalid extended color stops at that color, because its arguments cannot be told apart from codes.
+    pub fn apply_sgr_codes_lossy(&mut self, params: &[u16]) {
+        let _ = self.apply_codes(params, true);
+    }
+
+    fn apply_codes(&mut self, params: &[u16], lossy: bool) -> Result<(), SgrError> {
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            let basic = |base: Code, intensity| {
+                Color::Basic(BasicColor::new(base_color(param - base as u16), intensity))
+            };
+            let code = match param {
+                30..=37 => { self.fg_color = basic(Code::SetForegroundBase, Intensity::Normal); continue }
+                40..=47 => { self.bg_color = basic(Code::SetBackgroundBase, Intensity::Normal); continue }
+                90..=97 => { self.fg_color = basic(Code::SetBrightForegroundBase, Intensity::Bright); continue }
+                100..=107 => { self.bg_color = basic(Code::SetBrightBackgroundBase, Intensity::Bright); continue }
+                _ => match Code::from_param(param) {
+                    Some(code) => code,
+                    None if lossy => continue,
+                    None => return Err(SgrError::Unsupported(param)),
+                },
+            };
+            match code {
+                Code::Reset => *self = Style::default(),
+                Code::Bold => self.boldness = Boldness::Bold,
+                Code::Faint => self.boldness = Boldness::Faint,
+                Code::Italic => self.italic = true,
+                Code::Underline => self.underline = true,
+                Code::Blink => self.blink = true,
+                Code::Reverse => self.reverse = true,
+                Code::Conceal => self.conceal = true,
+                Code::Strike => self.strike = true,
+                Code::NoBoldness => self.boldness = Boldness::Normal,
+                Code::NoItalic => self.italic = false,
+                Code::NoUnderline => self.underline = false,
+                Code::NoBlink => self.blink = false,
+                Code::NoReverse => self.reverse = false,
+                Code::NoConceal => self.conceal = false,
+                Code::NoStrike => self.strike = false,
+                Code::SetForeground => self.fg_color = extended_color(&mut params)?,
+                Code::SetBackground => self.bg_color = extended_color(&mut params)?,
+                Code::DefaultForeground => self.fg_color = Color::Normal,
+                Code::DefaultBackground => self.bg_color = Color::Normal,
+                Code::SetForegroundBase | Code::SetBackgroundBase
+                | Code::SetBrightForegroundBase | Code::SetBrightBackgroundBase => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn base_color(index: u16) -> BaseColor {
+    BaseColor::ALL[index as usize]
+}
+
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Result<Color, SgrError> {
+    let mut component = || params.next()
+        .and_then(|c| u8::try_from(c).ok())
+        .ok_or(SgrError::InvalidColorArgs);
+    match component()? {
+        5 => Ok(color_256(component()?)),
+        2 => Ok(Color::Rgb(RgbColor { r: component()?, g: component()?, b: component()? })),
+        mode => Err(SgrError::InvalidColorMode(mode.into())),
+    }
+}
+
+/// Converts an index of the 256 color palette, the 16 basic colors stay basic
+/// and the color cube and grayscale ramp are converted to their rgb values.
+pub fn color_256(index: u8) -> Color {
+    match index {
+        0..=7 => Color::Basic(BasicColor::new(base_color(index.into()), Intensity::Normal)),
+        8..=15 => Color::Basic(BasicColor::new(base_color(u16::from(index) - 8), Intensity::Bright)),
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let i = index - 16;
+            Color::Rgb(RgbColor { r: level(i / 36), g: level(i / 6 % 6), b: level(i % 6) })
+        }
+        232..=255 => {
+            let v = 8 + (index - 232) * 10;
+            Color::Rgb(RgbColor { r: v, g: v, b: v })
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fmt, str::FromStr};
+
+use crate::{codes::{add_ansi_code, BaseColor, BasicColor, Code, Color, Intensity, RgbColor}, parse::{parse_commands, TemplateError}};
+
+macro_rules! diff {
+    ($($member: ident: $t: ty),*) => {
+        /// The complete set of attributes text is displayed with.
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct Style {
+            $( pub $member: $t ),*
+        }
+        impl Style {
+            fn diff(&self, other: &Self) -> StyleDiff {
+                StyleDiff {
+                    $(
+                        $member: (self.$member != other.$member).then_some(self.$member)
+                    ),*
+                }
+            }
+        }
+        #[derive(Debug)]
+        struct StyleDiff {
+            $( $member: Option<$t> ),*
+        }
+    };
+}
+diff! {
+    fg_color: Color,
+    bg_color: Color,
+    boldness: Boldness,
+    italic: bool,
+    underline: bool,
+    blink: bool,
+    reverse: bool,
+    conceal: bool,
+    strike: bool
+}
+impl Style {
+    pub fn is_default(&self) -> bool {
+        *self == Style::default()
+    }
+    /// Sets all attributes that are set in `other`, leaving the others as they are.
+    pub fn overlay(&mut self, other: &Style) {
+        macro_rules! overlay {
+            ($($member: ident),*) => {
+                $( if other.$member != Style::default().$member { self.$member = other.$member; } )*
+            };
+        }
+        overlay!(fg_color, bg_color, boldness, italic, underline, blink, reverse, conceal, strike);
+    }
+    /// Appends the escape codes that switch from the `applied` style to this one.
+    pub fn transition(&self, applied: &Style, s: &mut String) {
+        if self.is_default() && !applied.is_default() {
+            add_ansi_code(s, [Code::Reset as u8]);
+        } else {
+            self.diff(applied).apply(s);
+        }
+    }
+}
+impl StyleDiff {
+    fn apply(&self, s: &mut String) {
+        macro_rules! toggle {
+            ($member: ident, $t: ident, $f: ident) => {
+                if let Some(v) = self.$member {
+                    add_ansi_code(s, [if v { Code::$t } else { Code::$f } as u8]);
+                }
+            };
+        }
+        if let Some(fg_color) = self.fg_color { fg_color.ansi(s, false) }
+        if let Some(bg_color) = self.bg_color { bg_color.ansi(s, true) }
+        if let Some(boldness) = self.boldness { boldness.ansi(s) }
+        toggle!(italic, Italic, NoItalic);
+        toggle!(underline, Underline, NoUnderline);
+        toggle!(blink, Blink, NoBlink);
+        toggle!(reverse, Reverse, NoReverse);
+        toggle!(conceal, Conceal, NoConceal);
+        toggle!(strike, Strike, NoStrike);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Boldness { #[default] Normal, Bold, Faint }
+impl Boldness {
+    fn ansi(self, s: &mut String) {
+        add_ansi_code(s, [match self {
+            Boldness::Normal => Code::NoBoldness,
+            Boldness::Bold => Code::Bold,
+            Boldness::Faint => Code::Faint,
+        } as u8]);
+    }
+}
+
+/// Parses the inner syntax of a tag, for example `"bold;bright-red;bg:rgb(0,0,80)"`.
+///
+/// An empty string is the default style.
+impl FromStr for Style {
+    type Err = TemplateError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut style = Style::default();
+        if !s.trim().is_empty() {
+            for cmd in parse_commands(s, 0)? {
+                cmd.apply(&mut style);
+            }
+        }
+        Ok(style)
+    }
+}
+
+/// Writes the style in the canonical tag syntax that [`FromStr`] accepts, for example
+/// `bold;bright-red;bg:rgb(0,0,80)`. The alternate form `{:#}` uses the short names like `s;r!;_rgb(0,0,80)`.
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let short = f.alternate();
+        let mut first = true;
+        let mut cmd = |f: &mut fmt::Formatter<'_>, long: &str, short_name: &str| {
+            if !std::mem::take(&mut first) {
+                f.write_str(";")?;
+            }
+            f.write_str(if short { short_name } else { long })
+        };
+        match self.boldness {
+            Boldness::Normal => (),
+            Boldness::Bold => cmd(f, "bold", "s")?,
+            Boldness::Faint => cmd(f, "faint", "f")?,
+        }
+        macro_rules! flags {
+            ($($member: ident: $long: literal $short: literal),*) => {
+                $( if self.$member { cmd(f, $long, $short)?; } )*
+            };
+        }
+        flags!(
+            italic: "italic" "i", underline: "underline" "u", blink: "blink" "blink",
+            reverse: "reverse" "reverse", conceal: "conceal" "conceal", strike: "strike" "strike"
+        );
+        for (color, background) in [(self.fg_color, false), (self.bg_color, true)] {
+            let prefix = match (background, short) {
+                (false, _) => "",
+                (true, false) => "bg:",
+                (true, true) => "_",
+            };
+            let name = match color {
+                Color::Normal => continue,
+                Color::Basic(BasicColor { base, intensity }) => {
+                    let (long, short_name) = base.names();
+                    match (intensity, short) {
+                        (Intensity::Normal, false) => long.to_owned(),
+                        (Intensity::Normal, true) => short_name.to_owned(),
+                        (Intensity::Bright, false) => format!("bright-{}", long),
+                        (Intensity::Bright, true) => format!("{}!", short_name),
+                    }
+                }
+                Color::Rgb(RgbColor { r, g, b }) => format!("rgb({},{},{})", r, g, b),
+            };
+            let name = format!("{}{}", prefix, name);
+            cmd(f, &name, &name)?;
+        }
+        Ok(())
+    }
+}
+
+impl BaseColor {
+    /// The long and short name used in tags.
+    pub fn names(self) -> (&'static str, &'static str) {
+        match self {
+            BaseColor::Black => ("black", "k"),
+            BaseColor::Red => ("red", "r"),
+            BaseColor::Green => ("green", "g"),
+            BaseColor::Yellow => ("yellow", "y"),
+            BaseColor::Blue => ("blue", "b"),
+            BaseColor::Magenta => ("magenta", "m"),
+            BaseColor::Cyan => ("cyan", "c"),
+            BaseColor::White => ("white", "w"),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Style {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Style {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
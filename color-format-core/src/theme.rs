@@ -0,0 +1,130 @@
+//! Semantic tags like `#error<...>` whose concrete style is chosen at runtime from a [`Theme`].
+
+use std::sync::OnceLock;
+
+use crate::{parse::Cmd, style::Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Semantic {
+    Error,
+    Warn,
+    Path,
+    Code,
+}
+impl Semantic {
+    pub const ALL: [Semantic; 4] = [Semantic::Error, Semantic::Warn, Semantic::Path, Semantic::Code];
+
+    /// The name used as a tag.
+    pub fn name(self) -> &'static str {
+        match self {
+            Semantic::Error => "error",
+            Semantic::Warn => "warn",
+            Semantic::Path => "path",
+            Semantic::Code => "code",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|semantic| semantic.name() == name)
+    }
+}
+
+/// Text that marks the content of a tag when colors are off, like `[!] ` before an error.
+///
+/// Declared with `#tag(fallback="[!] ", fallback-end="...")<...>` or by the [`Theme`] for semantic tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Fallback<'a> {
+    pub before: &'a str,
+    pub after: &'a str,
+}
+impl<'a> Fallback<'a> {
+    pub fn new(before: &'a str, after: &'a str) -> Self {
+        Self { before, after }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty()
+    }
+}
+
+/// The styles of the semantic tags and their fallbacks for uncolored output.
+///
+/// Only the attributes a theme style sets are applied, so `#_w<#error<...>>` keeps the white background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub error: Style,
+    pub warn: Style,
+    pub path: Style,
+    pub code: Style,
+    fallbacks: [Fallback<'static>; 4],
+}
+impl Theme {
+    fn from_tags(error: &str, warn: &str, path: &str, code: &str) -> Self {
+        let style = |tags: &str| tags.parse().expect("built-in theme styles are valid");
+        Self {
+            error: style(error),
+            warn: style(warn),
+            path: style(path),
+            code: style(code),
+            fallbacks: [
+                Fallback::new("[!] ", ""),
+                Fallback::new("[warning] ", ""),
+                Fallback::new("'", "'"),
+                Fallback::new("`", "`"),
+            ],
+        }
+    }
+    /// For terminals with a dark background, the default.
+    pub fn dark() -> Self {
+        Self::from_tags("bold;bright-red", "bold;bright-yellow", "underline;bright-cyan", "bright-magenta")
+    }
+    /// For terminals with a light background, avoids the bright colors that are hard to read on white.
+    pub fn light() -> Self {
+        Self::from_tags("bold;red", "bold;rgb(175,95,0)", "underline;blue", "magenta")
+    }
+    /// Uses background colors and attributes instead of foreground colors alone.
+    pub fn high_contrast() -> Self {
+        Self::from_tags("bold;bright-white;bg:red", "bold;black;bg:bright-yellow", "bold;underline", "bold;reverse")
+    }
+
+    pub fn get(&self, semantic: Semantic) -> Style {
+        match semantic {
+            Semantic::Error => self.error,
+            Semantic::Warn => self.warn,
+            Semantic::Path => self.path,
+            Semantic::Code => self.code,
+        }
+    }
+    pub fn set(&mut self, semantic: Semantic, style: Style) {
+        match semantic {
+            Semantic::Error => self.error = style,
+            Semantic::Warn => self.warn = style,
+            Semantic::Path => self.path = style,
+            Semantic::Code => self.code = style,
+        }
+    }
+
+    /// The text around a semantic tag when colors are off.
+    pub fn fallback(&self, semantic: Semantic) -> Fallback<'static> {
+        self.fallbacks[semantic as usize]
+    }
+    pub fn set_fallback(&mut self, semantic: Semantic, fallback: Fallback<'static>) {
+        self.fallbacks[semantic as usize] = fallback;
+    }
+
+    /// The style that results from applying `cmds` in order, semantic commands use this theme.
+    pub fn resolve(&self, cmds: &[Cmd]) -> Style {
+        let mut style = Style::default();
+        for &cmd in cmds {
+            cmd.apply_themed(&mut style, self);
+        }
+        style
+    }
+}
+impl Default for Theme {
+    fn default() -> Self { *default_theme() }
+}
+
+/// The dark theme, parsed from its tags only once.
+pub(crate) fn default_theme() -> &'static Theme {
+    static DEFAULT: OnceLock<Theme> = OnceLock::new();
+    DEFAULT.get_or_init(Theme::dark)
+}
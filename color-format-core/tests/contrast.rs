@@ -0,0 +1,28 @@
+use color_format_core::{contrast_ratio, Color, RgbColor, Style};
+
+fn style(tags: &str) -> Style {
+    tags.parse().unwrap()
+}
+
+#[test]
+fn ratios() {
+    let black = RgbColor { r: 0, g: 0, b: 0 };
+    let white = RgbColor { r: 255, g: 255, b: 255 };
+    assert_eq!(contrast_ratio(black, white), 21.0);
+    assert_eq!(contrast_ratio(white, black), 21.0);
+    assert_eq!(contrast_ratio(white, white), 1.0);
+    let gray = RgbColor { r: 118, g: 118, b: 118 };
+    assert!((contrast_ratio(gray, white) - 4.54).abs() < 0.01);
+}
+
+#[test]
+fn style_contrast() {
+    assert_eq!(style("bright-white;bg:black").contrast_ratio(), Some(21.0));
+    assert!(style("w;_y!").contrast_ratio().unwrap() < 1.5);
+    assert!(style("rgb(0,0,80);_rgb(0,0,60)").contrast_ratio().unwrap() < 1.2);
+    // the default colors of the terminal are unknown
+    assert_eq!(style("bold;red").contrast_ratio(), None);
+    assert_eq!(style("bg:red").contrast_ratio(), None);
+    assert_eq!(Color::Normal.reference_rgb(), None);
+    assert_eq!(style("y").fg_color.reference_rgb(), Some(RgbColor { r: 205, g: 205, b: 0 }));
+}
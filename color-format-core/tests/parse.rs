@@ -0,0 +1,49 @@
+use color_format_core::*;
+
+#[test]
+fn string_parts() {
+    let parts: Vec<_> = StringParser::new("a #r;_b!<{x:>3}##> {{").collect::<Result<_, _>>().unwrap();
+    assert_eq!(parts, [
+        (0, StringPart::String("a ")),
+        (2, StringPart::StartCmd(vec![
+            Cmd::Color { color: Color::Basic(BasicColor::new(BaseColor::Red, Intensity::Normal)), background: false },
+            Cmd::Color { color: Color::Basic(BasicColor::new(BaseColor::Blue, Intensity::Bright)), background: true },
+        ])),
+        (9, StringPart::Placeholder("{x:>3}")),
+        (15, StringPart::String("#")),
+        (17, StringPart::EndCmd),
+        (18, StringPart::String(" ")),
+        (19, StringPart::String("{{")),
+    ]);
+}
+
+#[test]
+fn transitions() {
+    let mut style = Style::default();
+    for cmd in parse_commands("s;u;rgb(1,2,3)", 0).unwrap() {
+        cmd.apply(&mut style);
+    }
+    let mut codes = String::new();
+    style.transition(&Style::default(), &mut codes);
+    assert_eq!(codes, "\x1b[38;2;1;2;3m\x1b[1m\x1b[4m");
+    codes.clear();
+    Style::default().transition(&style, &mut codes);
+    assert_eq!(codes, "\x1b[0m");
+}
+
+#[test]
+fn fallbacks() {
+    let parts: Vec<_> = StringParser::new("#r;s(fallback = '[!] ' )<x>").collect::<Result<_, _>>().unwrap();
+    let attributes = Attributes { fallback: Some(Fallback::new("[!] ", "")), link: None };
+    assert_eq!(parts[1], (4, StringPart::Attributes(attributes)));
+    assert_eq!(parts.len(), 4);
+    let parts: Vec<_> = StringParser::new("#(link='https://example.com')<x>").collect::<Result<_, _>>().unwrap();
+    assert_eq!(parts[0], (0, StringPart::StartCmd(Vec::new())));
+    assert_eq!(parts[1].1, StringPart::Attributes(Attributes { fallback: None, link: Some("https://example.com") }));
+    let error = |s| StringParser::new(s).find_map(Result::err).unwrap();
+    let e = error("#r(nope='x')<a>");
+    assert_eq!((e.kind, e.span), (TemplateErrorKind::InvalidAttribute("nope".to_owned()), 3..11));
+    assert_eq!(error("#r(fallback='x'<a>").kind, TemplateErrorKind::UnclosedAttributes);
+    assert_eq!(error("#r(fallback='x<a>").kind, TemplateErrorKind::UnclosedAttributes);
+    assert_eq!(error("#r(fallback='x') a").kind, TemplateErrorKind::MissingOpen);
+}
@@ -0,0 +1,48 @@
+use color_format_core::*;
+
+#[test]
+fn from_str() {
+    let style: Style = "bold; bright-red;bg:rgb(0,0,80)".parse().unwrap();
+    assert_eq!(style, Style {
+        boldness: Boldness::Bold,
+        fg_color: Color::Basic(BasicColor::new(BaseColor::Red, Intensity::Bright)),
+        bg_color: Color::Rgb(RgbColor { r: 0, g: 0, b: 80 }),
+        ..Style::default()
+    });
+    assert_eq!(style.to_string(), "bold;bright-red;bg:rgb(0,0,80)");
+    assert_eq!(format!("{:#}", style), "s;r!;_rgb(0,0,80)");
+    assert_eq!("".parse::<Style>().unwrap(), Style::default());
+    assert_eq!("u;i;_g;f;strike".parse::<Style>().unwrap().to_string(), "faint;italic;underline;strike;bg:green");
+    let e = "bold;nope".parse::<Style>().unwrap_err();
+    assert_eq!((e.kind, e.span), (TemplateErrorKind::UnknownTag("nope".to_owned()), 5..9));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    let style: Style = "s;_b".parse().unwrap();
+    assert_eq!(serde_json::to_string(&style).unwrap(), r#""bold;bg:blue""#);
+    assert_eq!(serde_json::from_str::<Style>(r#""bold;bg:blue""#).unwrap(), style);
+    assert!(serde_json::from_str::<Style>(r#""bold;bg:bold""#).is_err());
+}
+
+#[test]
+fn colorblind_safe() {
+    let style: Style = "bold;red;bg:green!".parse().unwrap();
+    assert_eq!(style.colorblind_safe(true).to_string(), "bold;rgb(230,159,0);bg:rgb(86,180,233)");
+    assert_eq!(style.colorblind_safe(false).to_string(), "bold;yellow;bg:bright-blue");
+    let other: Style = "c;_rgb(200,0,0)".parse().unwrap();
+    assert_eq!(other.colorblind_safe(true), other);
+}
+
+#[test]
+fn palette() {
+    assert_eq!(RgbColor::from_hex("#ff8000"), Some(RgbColor { r: 255, g: 128, b: 0 }));
+    assert_eq!(RgbColor::from_hex("ff8000"), None);
+    assert_eq!(RgbColor::from_hex("#ff80"), None);
+    assert_eq!(RgbColor { r: 1, g: 2, b: 255 }.to_hex(), "#0102ff");
+    let palette = Palette::xterm();
+    assert_eq!(palette.get(BasicColor::new(BaseColor::Blue, Intensity::Bright)), RgbColor { r: 92, g: 92, b: 255 });
+    assert_eq!(palette.rgb(Color::Normal), None);
+    assert_eq!(palette.iter().count(), 16);
+}
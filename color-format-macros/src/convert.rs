@@ -1,166 +1,629 @@
-use crate::{codes::{Color, self, Code, add_ansi_code}, parse::{StringParser, StringPart, Cmd}};
+use std::ops::Range;
+
+use color_format_core::{
+    html::escape_html, roff::escape_roff, Aliases, Cmd, Fallback, HtmlOptions, RoffOptions, Semantic, StringParser,
+    StringPart, Style, SECRET_MASK,
+};
 use proc_macro::Span;
-use quote::quote;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
 use syn::{Ident, Expr, LitStr, punctuated::Punctuated, Token};
 
+use crate::{manifest::{Contrast, Level}, sanitize::Rewriter};
+
+
+pub(crate) fn colored_macro(
+    f: Option<Expr>, fmt: LitStr, args: Punctuated<Expr, Token![,]>, emitted_macro: &str, sanitize: bool,
+) -> proc_macro::TokenStream {
+    expand(Target::Macro { f: f.as_ref(), name: emitted_macro }, fmt, args, sanitize)
+        .unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+pub(crate) fn styled_macro(fmt: LitStr, args: Punctuated<Expr, Token![,]>, sanitize: bool) -> proc_macro::TokenStream {
+    expand(Target::Styled, fmt, args, sanitize).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+pub(crate) fn html_macro(fmt: LitStr, args: Punctuated<Expr, Token![,]>, sanitize: bool) -> proc_macro::TokenStream {
+    expand(Target::Html, fmt, args, sanitize).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+pub(crate) fn roff_macro(fmt: LitStr, args: Punctuated<Expr, Token![,]>, sanitize: bool) -> proc_macro::TokenStream {
+    expand(Target::Roff, fmt, args, sanitize).unwrap_or_else(|e| e.to_compile_error()).into()
+}
+
+enum Target<'a> {
+    /// Expands to `name!(...)`, writing to `f` if given.
+    Macro { f: Option<&'a Expr>, name: &'a str },
+    /// Expands to a `color_format::StyledText`.
+    Styled,
+    /// Expands to a `String` of HTML, see `[package.metadata.color-format.html]`.
+    Html,
+    /// Expands to a `String` of roff, see `[package.metadata.color-format.roff]`.
+    Roff,
+}
+
+fn expand(target: Target, fmt: LitStr, args: Punctuated<Expr, Token![,]>, sanitize: bool)
+-> syn::Result<TokenStream> {
+    let expanded = expand_target(target, fmt, args, sanitize)?;
+    let track = crate::manifest::track();
+    Ok(if track.is_empty() { expanded } else { quote! {{ #track #expanded }} })
+}
 
-pub(crate) fn colored_macro(f: Option<Expr>, fmt: LitStr, args: Punctuated<Expr, Token![,]>, emitted_macro: &str)
--> proc_macro::TokenStream {
-    let (fmt_str, unformatted_str) = colored_fmt_string(&fmt.value());
+fn expand_target(target: Target, fmt: LitStr, args: Punctuated<Expr, Token![,]>, sanitize: bool)
+-> syn::Result<TokenStream> {
+    let settings = crate::manifest::settings().map_err(|e| syn::Error::new(fmt.span(), e))?;
+    let mut rewriter = Rewriter::new(&args, &fmt)?;
+    let strings = colored_fmt_string(&fmt, &settings.aliases, false, &mut |p, _| rewriter.placeholder(p))?;
+    let warning = check_contrast(&strings.styles, settings.contrast, &fmt)?;
+    let styled = matches!(target, Target::Styled);
+    let builder = syn::parse_quote!(__cf_builder);
+    let (f, emitted_macro) = match target {
+        Target::Macro { f, name } => (f, name),
+        Target::Styled => (Some(&builder), "write"),
+        Target::Html | Target::Roff => (None, "format"),
+    };
+    // values in HTML and roff are escaped by `Html` and `Roff` instead of `Sanitized`
+    let wrapper = match target {
+        Target::Html => format_ident!("Html"),
+        Target::Roff => format_ident!("Roff"),
+        _ => format_ident!("Sanitized"),
+    };
     let macro_ident = Ident::new(emitted_macro, Span::call_site().into());
-    let fmt_args = args.iter();
-    let fmt_args2 = args.iter();
-    let f = f.map_or_else(|| quote!{}, |f| quote!{ #f, });
-    #[cfg(feature = "runtime_color")]
-    {
-        quote! {
-            if ::color_format::config::config().colorize() {
-                #macro_ident!(#f #fmt_str, #(#fmt_args),*)
-            } else {
-                #macro_ident!(#f #unformatted_str, #(#fmt_args2),*)
+    // the writer of `cwrite!` is evaluated once and reborrowed like `write!` does, see `AsWriter`
+    let bound_writer = match target {
+        Target::Macro { f: Some(f), .. } => Some(f),
+        _ => None,
+    };
+    let writer = match bound_writer {
+        Some(_) => Some(quote!{ __cf_w, }),
+        None => f.map(|f| quote!{ #f, }),
+    };
+    // interpolated values go through `Untrusted` and the output through `Sanitized`, see `#[sanitize]`
+    let call = |fmt_str: &str, extra: Vec<TokenStream>| {
+        // the uncolored variant leaves out the values in `#secret<...>` tags
+        let args = rewriter.args_for(fmt_str);
+        if rewriter.is_empty() {
+            quote! { #macro_ident!(#writer #fmt_str #(, #extra)*) }
+        } else {
+            quote! {
+                #macro_ident!(#writer "{}", ::color_format::__private::#wrapper(
+                    format_args!(#fmt_str #(, #args)* #(, #extra)*), #sanitize
+                ))
             }
-        }.into()
+        }
+    };
+    let markers = marker_args(&strings.markers);
+    if let Target::Html | Target::Roff = target {
+        let fmt_str = match target {
+            Target::Html => strings.styled.html(&settings.html),
+            _ => strings.styled.roff(&settings.roff),
+        };
+        let markup_call = call(&fmt_str, Vec::new());
+        let expanded = quote! {{
+            #warning
+            #markup_call
+        }};
+        return if args.is_empty() && rewriter.is_empty() { Ok(expanded) } else { rewriter.bind(expanded) };
     }
-    #[cfg(not(feature = "runtime_color"))]
-    { quote! { #macro_ident!(#f #fmt_str, #(#fmt_args),*) }.into() }
+    if styled {
+        let segments = &strings.styled.segments;
+        let styles = segments.iter().map(|segment| {
+            let cmds = segment.cmds.iter().map(crate::tokens::cmd);
+            quote! { __cf_theme.resolve(&[#(#cmds),*]) }
+        });
+        let metas = segments.iter().map(|segment| {
+            let link = match &segment.link {
+                Some(link) => quote! { ::std::option::Option::Some(#link) },
+                None => quote! { ::std::option::Option::None },
+            };
+            let kind = format_ident!("{}", segment.kind);
+            quote! { (#link, ::color_format::SpanKind::#kind) }
+        });
+        let switches = (0..segments.len()).map(|i| {
+            let name = format_ident!("__cf_s{}", i);
+            quote! { #name = ::color_format::__private::Segment(&__cf_segment, #i) }
+        });
+        let styled_call = call(&strings.styled.fmt_str(), switches.chain(markers).collect());
+        #[cfg(feature = "runtime_color")]
+        let prelude = quote! {
+            let __cf_theme = ::color_format::__private::theme();
+            let __cf_styles = ::color_format::__private::adjust([#(#styles),*]);
+        };
+        #[cfg(not(feature = "runtime_color"))]
+        let prelude = quote! {
+            let __cf_theme = ::color_format::Theme::default();
+            let __cf_styles = [#(#styles),*];
+        };
+        let expanded = quote! {{
+            #warning
+            #prelude
+            let __cf_segment = ::std::cell::Cell::new(0);
+            let mut __cf_builder = ::color_format::__private::StyledBuilder::new(
+                &__cf_styles, &[#(#metas),*], &__cf_segment,
+            );
+            #styled_call;
+            __cf_builder.finish()
+        }};
+        return if args.is_empty() && rewriter.is_empty() { Ok(expanded) } else { rewriter.bind(expanded) };
+    }
+    let expanded = colored_expansion(strings, bound_writer.is_some(), &|fmt_str, extra, _| call(fmt_str, extra));
+    let expanded = if args.is_empty() && rewriter.is_empty() { expanded } else { rewriter.bind(expanded)? };
+    // without `#[sanitize]` the placeholders are passed on as they are, so that std parses their format specs,
+    // and the rewritten ones are only used while sanitizing is turned on with `config::set_sanitize`
+    let expanded = if sanitize || rewriter.is_empty() {
+        expanded
+    } else {
+        // the uncolored variant keeps the placeholders in `#secret<...>` tags, which `format!` would reject as
+        // unused otherwise, and passes `Hidden` for their values
+        let mut scan = Rewriter::new(&args, &fmt)?;
+        let mut references = Vec::new();
+        let strings = colored_fmt_string(&fmt, &settings.aliases, true, &mut |p, secret| {
+            references.push((scan.references(p)?, secret));
+            Ok(without_raw(p))
+        })?;
+        let mut hidden = vec![false; scan.len()];
+        let mut shown = vec![false; scan.len()];
+        for ((value, counts), secret) in &references {
+            let values = if *secret { &mut hidden } else { &mut shown };
+            values[*value] = true;
+            counts.iter().for_each(|&count| shown[count] = true);
+        }
+        let plain_args = scan.hidden_args(&hidden);
+        let direct = |fmt_str: &str, extra: Vec<TokenStream>, plain: bool| {
+            let args = if plain { plain_args.clone() } else { args.iter().map(|arg| quote! { #arg }).collect() };
+            quote! { #macro_ident!(#writer #fmt_str #(, #args)* #(, #extra)*) }
+        };
+        // a value that is shown elsewhere too cannot be hidden, those are left to the rewritten placeholders
+        if hidden.iter().zip(&shown).any(|(hidden, shown)| *hidden && *shown) {
+            expanded
+        } else {
+            let direct = colored_expansion(strings, bound_writer.is_some(), &direct);
+            #[cfg(feature = "runtime_color")]
+            let expanded = quote! {
+                if ::color_format::__private::sanitize(false) { #expanded } else { #direct }
+            };
+            #[cfg(not(feature = "runtime_color"))]
+            let expanded = direct;
+            expanded
+        }
+    };
+    Ok(match bound_writer {
+        Some(f) => quote! {{
+            #warning
+            use ::color_format::__private::AsWriter as _;
+            match (#f).__cf_writer() {
+                __cf_w => #expanded
+            }
+        }},
+        None => quote! {{
+            #warning
+            #expanded
+        }},
+    })
 }
 
-macro_rules! diff {
-    ($($member: ident: $t: ty),*) => {
-        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
-        struct State {
-            $( $member: $t ),*
-        }
-        impl State {
-            fn diff(&self, other: &Self) -> StateDiff {
-                StateDiff {
-                    $(
-                        $member: (self.$member != other.$member).then(|| self.$member)
-                    ),*
-                } 
+/// The expansion for `cformat!` and the other macros that write with colors or without, with the arguments of
+/// the emitted macro added by `call`, which is told whether it is for the uncolored variant.
+/// `cwrite!` binds its writer to `__cf_w`.
+#[cfg_attr(not(feature = "runtime_color"), allow(unused_variables))]
+fn colored_expansion(
+    strings: FmtStrings, writer: bool, call: &dyn Fn(&str, Vec<TokenStream>, bool) -> TokenStream,
+) -> TokenStream {
+    #[cfg(feature = "runtime_color")]
+    let expanded = {
+        let markers = marker_args(&strings.markers);
+        let colorize = if writer {
+            // writers can carry their own decision, see `color_format::ColorChoice`
+            quote! {{
+                #[allow(unused_imports)]
+                use ::color_format::__private::{WriterChoice as _, GlobalChoice as _};
+                (&::color_format::__private::Probe(&*__cf_w)).colorize()
+            }}
+        } else {
+            quote! { ::color_format::__private::colorize() }
+        };
+        let FmtStrings { colored, plain, dynamic, semantic, styles, .. } = strings;
+        let Dynamic { fmt_str, segments, transitions } = dynamic;
+        let static_colored = call(&colored, Vec::new(), false);
+        // semantic tags and colorblind-safe colors are resolved when formatting
+        let styles_tokens = segments.iter().map(|cmds| {
+            let cmds = cmds.iter().map(crate::tokens::cmd);
+            quote! { __cf_theme.resolve(&[#(#cmds),*]) }
+        });
+        let transitions = (0..transitions).map(|i| {
+            let name = format_ident!("__cf_t{}", i);
+            quote! { #name = ::color_format::__private::Transition(&__cf_styles, #i) }
+        }).collect();
+        let dynamic_call = call(&fmt_str, transitions, false);
+        let dynamic = quote! {{
+            let __cf_theme = ::color_format::__private::theme();
+            let __cf_styles = ::color_format::__private::adjust([#(#styles_tokens),*]);
+            #dynamic_call
+        }};
+        let colored = if semantic {
+            dynamic
+        } else if styles.iter().any(|style| style.colorblind_safe(true) != *style) {
+            quote! {
+                if ::color_format::__private::colorblind() { #dynamic } else { #static_colored }
             }
-        }
-        #[derive(Debug)]
-        struct StateDiff {
-            $( $member: Option<$t> ),*
-        }
-        impl StateDiff {
-            fn diff_count(&self) -> usize {
-                let mut c = 0;
-                $( if self.$member.is_some() { c += 1; } )*
-                c
+        } else {
+            static_colored
+        };
+        let plain = if markers.is_empty() {
+            call(&plain, Vec::new(), true)
+        } else {
+            let plain_call = call(&plain, markers, true);
+            quote! {{
+                let __cf_theme = ::color_format::__private::theme();
+                #plain_call
+            }}
+        };
+        quote! {{
+            if #colorize {
+                #colored
+            } else {
+                #plain
             }
-        }
+        }}
     };
+    // without runtime configuration semantic tags always use the default theme
+    #[cfg(not(feature = "runtime_color"))]
+    let expanded = call(&strings.colored, Vec::new(), false);
+    expanded
+}
+
+fn marker_args(markers: &[(Semantic, bool)]) -> Vec<TokenStream> {
+    markers.iter().enumerate().map(|(i, &(semantic, after))| {
+        let name = format_ident!("__cf_f{}", i);
+        let semantic = crate::tokens::semantic(semantic);
+        let field = if after { format_ident!("after") } else { format_ident!("before") };
+        quote! { #name = __cf_theme.fallback(#semantic).#field }
+    }).collect()
 }
-diff! {
-    fg_color: Color,
-    bg_color: Color,
-    boldness: Boldness,
-    italic: bool,
-    underline: bool,
-    blink: bool,
-    reverse: bool,
-    conceal: bool,
-    strike: bool
-}
-impl State {
-    pub fn is_default(&self) -> bool {
-        *self == State::default()
+
+/// The placeholder without the `!raw` of `{:!raw}`, which only means something to the rewritten placeholders.
+fn without_raw(placeholder: &str) -> String {
+    match placeholder.strip_suffix("!raw}") {
+        Some(placeholder) => format!("{}}}", placeholder),
+        None => placeholder.to_owned(),
     }
 }
-impl StateDiff {
-    fn apply(&self, s: &mut String) {
-        macro_rules! toggle {
-            ($member: ident, $t: ident, $f: ident) => {
-                if let Some(v) = self.$member {
-                    add_ansi_code(s, [if v { Code::$t } else { Code::$f } as u8]);
-                }
-            };
+
+struct FmtStrings {
+    /// Escape codes inserted directly, semantic tags use the default theme.
+    colored: String,
+    /// All escapes taken out and fallbacks inserted, with a `{__cf_fN}` argument for the `N`th marker.
+    plain: String,
+    /// The markers of semantic tags from the theme, `true` for the text after the tag.
+    markers: Vec<(Semantic, bool)>,
+    /// For styles that are only known when formatting.
+    dynamic: Dynamic,
+    /// For `cformat_styled!`, with the same markers as `plain`.
+    styled: Styled,
+    semantic: bool,
+    /// The styles that text is printed in, semantic tags use the default theme.
+    styles: Vec<Style>,
+}
+
+/// A format string with a `{__cf_tN}` argument before segment `N` and after the last one,
+/// segments are the commands of all open tags.
+struct Dynamic {
+    fmt_str: String,
+    segments: Vec<Vec<Cmd>>,
+    transitions: usize,
+}
+
+/// The text of each segment of a `StyledText`.
+struct Styled {
+    /// The segment of each piece of text and `true` for literal text, `false` for placeholders.
+    pieces: Vec<(usize, String, bool)>,
+    segments: Vec<Segment>,
+}
+
+/// The commands of all open tags, the link and the `color_format::SpanKind` of a span.
+#[derive(PartialEq)]
+struct Segment {
+    cmds: Vec<Cmd>,
+    link: Option<String>,
+    kind: &'static str,
+}
+
+impl Styled {
+    fn push(&mut self, s: &str, literal: bool, segment: Segment) {
+        if s.is_empty() {
+            return;
         }
-        if let Some(fg_color) = self.fg_color { fg_color.ansi(s, false) }
-        if let Some(bg_color) = self.bg_color { bg_color.ansi(s, true) }
-        if let Some(boldness) = self.boldness { boldness.ansi(s) }
-        toggle!(italic, Italic, NoItalic);
-        toggle!(underline, Underline, NoUnderline);
-        toggle!(blink, Blink, NoBlink);
-        toggle!(reverse, Reverse, NoReverse);
-        toggle!(conceal, Conceal, NoConceal);
-        toggle!(strike, Strike, NoStrike);
+        let index = self.segments.iter().position(|other| *other == segment).unwrap_or_else(|| {
+            self.segments.push(segment);
+            self.segments.len() - 1
+        });
+        self.pieces.push((index, s.to_owned(), literal));
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Boldness { Normal, Bold, Faint }
-impl Boldness {
-    fn ansi(self, s: &mut String) {
-        codes::add_ansi_code(s, [match self {
-            Boldness::Normal => Code::NoBoldness,
-            Boldness::Bold => Code::Bold,
-            Boldness::Faint => Code::Faint,
-        } as u8]);
+    /// A format string with a `{__cf_sN}` argument before the text of segment `N`.
+    fn fmt_str(&self) -> String {
+        let mut fmt_str = String::new();
+        let mut current = None;
+        for (index, s, _) in &self.pieces {
+            if current != Some(index) {
+                fmt_str.push_str(&format!("{{__cf_s{}}}", index));
+                current = Some(index);
+            }
+            fmt_str.push_str(s);
+        }
+        fmt_str
+    }
+
+    /// A format string for HTML, semantic tags use the default theme.
+    ///
+    /// Literal text is escaped here and the values when formatting.
+    fn html(&self, options: &HtmlOptions) -> String {
+        let mut fmt_str = String::new();
+        let mut applied = (Style::default(), None);
+        let mut tags = String::new();
+        for (index, s, literal) in &self.pieces {
+            let segment = &self.segments[*index];
+            // the markup takes the place of the fallbacks
+            if matches!(segment.kind, "Fallback" | "Secret") {
+                continue;
+            }
+            let mut style = Style::default();
+            segment.cmds.iter().for_each(|cmd| cmd.apply(&mut style));
+            let state = (style, segment.link.as_deref());
+            tags.clear();
+            options.transition((&applied.0, applied.1), (&state.0, state.1), &mut tags);
+            fmt_str.push_str(&tags.replace('{', "{{").replace('}', "}}"));
+            applied = state;
+            if *literal {
+                escape_html(s, &mut fmt_str);
+            } else {
+                fmt_str.push_str(s);
+            }
+        }
+        tags.clear();
+        options.transition((&applied.0, applied.1), (&Style::default(), None), &mut tags);
+        fmt_str.push_str(&tags.replace('{', "{{").replace('}', "}}"));
+        fmt_str
+    }
+
+    /// A format string for roff, semantic tags use the default theme.
+    ///
+    /// Literal text is escaped here and the values when formatting.
+    fn roff(&self, options: &RoffOptions) -> String {
+        let mut fmt_str = String::new();
+        let mut applied = Style::default();
+        for (index, s, literal) in &self.pieces {
+            let segment = &self.segments[*index];
+            if matches!(segment.kind, "Fallback" | "Secret") {
+                continue;
+            }
+            let mut style = Style::default();
+            segment.cmds.iter().for_each(|cmd| cmd.apply(&mut style));
+            options.transition(&applied, &style, &mut fmt_str);
+            applied = style;
+            if *literal {
+                // after a value the line start is checked when formatting
+                escape_roff(s, fmt_str.is_empty() || fmt_str.ends_with('\n'), &mut fmt_str);
+            } else {
+                fmt_str.push_str(s);
+            }
+        }
+        options.transition(&applied, &Style::default(), &mut fmt_str);
+        fmt_str
     }
-}
-impl Default for Boldness {
-    fn default() -> Self { Self::Normal }
 }
 
 /// Takes in a format string literal possibly containing color escapes like #green { ... }
-/// and converts them to a string with ansi escapes. Also returns a String with all escapes just taken out.
-fn colored_fmt_string(s: &str) -> (String, String) {
-    let parser = StringParser::new(s);
+/// and converts them to a string with ansi escapes.
+///
+/// Placeholders are replaced with the result of `placeholder`, which is told whether they are in a `#secret<...>`
+/// tag. Those are left out of the uncolored variant unless `keep_secrets` is set.
+fn colored_fmt_string(
+    fmt: &LitStr, aliases: &Aliases, keep_secrets: bool,
+    placeholder: &mut dyn FnMut(&str, bool) -> Result<String, String>,
+) -> syn::Result<FmtStrings> {
+    let s = fmt.value();
+    let parser = StringParser::with_aliases(&s, aliases);
     let mut out_str = String::new();
     let mut unformatted = String::new();
+    let mut dynamic = Dynamic { fmt_str: String::new(), segments: Vec::new(), transitions: 0 };
+    let mut styled = Styled { pieces: Vec::new(), segments: Vec::new() };
+    let mut semantic = false;
     let mut states = Vec::new();
-    let mut applied_state = State::default();
-    let mut state = State::default();
+    let mut applied_state = Style::default();
+    let mut state = Style::default();
+    let mut cmd_states = Vec::new();
+    let mut cmd_state = Vec::new();
+    let mut styles = Vec::new();
+    let mut markers = Vec::new();
+    let mut fallbacks = Vec::new();
+    let mut fallback_pending = false;
+    // for each open tag whether it is inside of a secret one and whether it is secret itself
+    let mut secrets: Vec<(bool, bool)> = Vec::new();
+    let mut links = Vec::new();
+    let mut link = None;
+    let mut applied_link = None;
+    let segment = |cmds: &[Cmd], link: Option<&str>, kind| Segment {
+        cmds: cmds.to_vec(), link: link.map(str::to_owned), kind,
+    };
     for item in parser {
-        match item {
-            StringPart::String(s) => {
-                if state.is_default() && !applied_state.is_default() {
-                    add_ansi_code(&mut out_str, [Code::Reset as u8]);
-                } else {
-                    let diff = state.diff(&applied_state);
-                    diff.apply(&mut out_str);
+        let part = item.map_err(|e| syn::Error::new(value_span(fmt, e.span.clone()), e))?.1;
+        if !matches!(part, StringPart::Attributes(_)) && std::mem::take(&mut fallback_pending) {
+            let fallback = fallbacks.last().expect("a tag was opened");
+            let &(in_secret, secret) = secrets.last().expect("a tag was opened");
+            if !in_secret {
+                let outer = &cmd_state[..*cmd_states.last().expect("a tag was opened")];
+                let outer_link = *links.last().expect("a tag was opened");
+                let text = write_fallback(fallback, false, &mut markers);
+                unformatted.push_str(&text);
+                styled.push(&text, true, segment(outer, outer_link, "Fallback"));
+                if secret {
+                    unformatted.push_str(SECRET_MASK);
+                    styled.push(SECRET_MASK, true, segment(outer, outer_link, "Mask"));
                 }
+            }
+        }
+        match part {
+            part @ (StringPart::String(_) | StringPart::Placeholder(_)) => {
+                let literal = matches!(part, StringPart::String(_));
+                let secret = cmd_state.contains(&Cmd::Secret);
+                let s = &match part {
+                    StringPart::Placeholder(p) => placeholder(p, secret).map_err(|e| syn::Error::new(fmt.span(), e))?,
+                    StringPart::String(s) => s.to_owned(),
+                    _ => unreachable!(),
+                };
+                state.transition(&applied_state, &mut out_str);
                 applied_state = state;
+                if !styles.contains(&state) {
+                    styles.push(state);
+                }
+                let current = dynamic.segments.last().map_or(&[][..], Vec::as_slice);
+                if current != cmd_state.as_slice() {
+                    dynamic.fmt_str.push_str(&format!("{{__cf_t{}}}", dynamic.segments.len()));
+                    dynamic.segments.push(cmd_state.clone());
+                }
+                if link != applied_link {
+                    let escape = hyperlink(link);
+                    out_str.push_str(&escape);
+                    dynamic.fmt_str.push_str(&escape);
+                    applied_link = link;
+                }
                 out_str.push_str(s);
-                unformatted.push_str(s);
+                if !secret || (keep_secrets && !literal) {
+                    unformatted.push_str(s);
+                }
+                dynamic.fmt_str.push_str(s);
+                styled.push(s, literal, segment(&cmd_state, link, if secret { "Secret" } else { "Text" }));
             }
             StringPart::StartCmd(cmds) => {
                 states.push(state);
+                cmd_states.push(cmd_state.len());
+                links.push(link);
+                let semantics = cmds.iter()
+                    .filter_map(|cmd| match cmd { Cmd::Semantic(s) => Some(*s), _ => None })
+                    .collect::<Vec<_>>();
+                semantic |= !semantics.is_empty();
+                fallbacks.push(TagFallback::Semantic(semantics));
+                fallback_pending = true;
+                let in_secret = cmd_state.contains(&Cmd::Secret);
+                secrets.push((in_secret, in_secret || cmds.contains(&Cmd::Secret)));
                 for cmd in cmds {
-                    match cmd {
-                        Cmd::Color { color, background: false } => state.fg_color = color,
-                        Cmd::Color { color, background: true }  => state.bg_color = color,
-                        Cmd::Bold => state.boldness = Boldness::Bold,
-                        Cmd::Faint => state.boldness = Boldness::Faint,
-                        Cmd::Underline => state.underline = true,
-                        Cmd::Strike => state.strike = true,
-                        Cmd::Reverse => state.reverse = true,
-                        Cmd::Conceal => state.conceal = true,
-                        Cmd::Italic => state.italic = true,
-                        Cmd::Blink => state.blink = true,
-                        
-                    }
+                    cmd.apply(&mut state);
+                    cmd_state.push(cmd);
+                }
+            }
+            StringPart::Attributes(attributes) => {
+                if let Some(fallback) = attributes.fallback {
+                    *fallbacks.last_mut().expect("attributes follow their tag") = TagFallback::Explicit(fallback);
+                }
+                if attributes.link.is_some() {
+                    link = attributes.link;
                 }
             }
             StringPart::EndCmd => {
-                if let Some(prev) = states.pop() {
-                    state = prev;
-                } else {
-                    panic!("Mismatched closing '}}'");
+                let fallback = fallbacks.pop().expect("parser checks that tags are balanced");
+                let (in_secret, _) = secrets.pop().expect("parser checks that tags are balanced");
+                state = states.pop().expect("parser checks that tags are balanced");
+                cmd_state.truncate(cmd_states.pop().expect("parser checks that tags are balanced"));
+                link = links.pop().expect("parser checks that tags are balanced");
+                if !in_secret {
+                    let text = write_fallback(&fallback, true, &mut markers);
+                    unformatted.push_str(&text);
+                    styled.push(&text, true, segment(&cmd_state, link, "Fallback"));
                 }
             }
         }
     }
-    let end_diff = state.diff(&applied_state);
-    if end_diff.diff_count() != 0 {
-        add_ansi_code(&mut out_str, [Code::Reset as u8]);
+    state.transition(&applied_state, &mut out_str);
+    dynamic.transitions = dynamic.segments.len();
+    if dynamic.segments.last().is_some_and(|cmds| !cmds.is_empty()) {
+        dynamic.fmt_str.push_str(&format!("{{__cf_t{}}}", dynamic.segments.len()));
+        dynamic.transitions += 1;
+    }
+    if applied_link.is_some() {
+        let escape = hyperlink(None);
+        out_str.push_str(&escape);
+        dynamic.fmt_str.push_str(&escape);
+    }
+    Ok(FmtStrings { colored: out_str, plain: unformatted, markers, dynamic, styled, semantic, styles })
+}
+
+/// The span of the bytes `range` of the value of `fmt`, where the compiler supports it and the literal has no
+/// escapes, and of the whole literal otherwise.
+fn value_span(fmt: &LitStr, range: Range<usize>) -> proc_macro2::Span {
+    let token = fmt.token();
+    let source = token.to_string();
+    let value = fmt.value();
+    // after the `"` or `r#"`
+    let start = source.find('"').map_or(0, |quote| quote + 1);
+    if source.get(start..start + value.len()) != Some(value.as_str()) {
+        return fmt.span();
+    }
+    token.subspan(start + range.start..start + range.end).unwrap_or_else(|| fmt.span())
+}
+
+/// The OSC 8 escape for `link`, with braces escaped for format strings.
+fn hyperlink(link: Option<&str>) -> String {
+    let mut escape = String::new();
+    color_format_core::codes::add_hyperlink(&mut escape, link);
+    escape.replace('{', "{{").replace('}', "}}")
+}
+
+/// What is written around a tag in the uncolored variant.
+enum TagFallback<'a> {
+    /// Declared with `#tag(fallback="...")<`.
+    Explicit(Fallback<'a>),
+    /// The markers of the theme for the semantic tags among the commands.
+    Semantic(Vec<Semantic>),
+}
+
+/// The text written for `fallback`, with a `{__cf_fN}` argument for each marker of the theme.
+fn write_fallback(fallback: &TagFallback, after: bool, markers: &mut Vec<(Semantic, bool)>) -> String {
+    match fallback {
+        TagFallback::Explicit(fallback) => {
+            let text = if after { fallback.after } else { fallback.before };
+            text.replace('{', "{{").replace('}', "}}")
+        }
+        TagFallback::Semantic(semantics) => {
+            let mut out = String::new();
+            let mut marker = |semantic| {
+                out.push_str(&format!("{{__cf_f{}}}", markers.len()));
+                markers.push((semantic, after));
+            };
+            if after {
+                semantics.iter().rev().for_each(|&semantic| marker(semantic));
+            } else {
+                semantics.iter().for_each(|&semantic| marker(semantic));
+            }
+            out
+        }
+    }
+}
+
+/// Checks the contrast of all styles that set both colors, see `[package.metadata.color-format.contrast]`.
+///
+/// There are no custom warnings on stable, so a warning is the use of a deprecated item.
+fn check_contrast(styles: &[Style], contrast: Contrast, fmt: &LitStr) -> syn::Result<TokenStream> {
+    if contrast.level == Level::Allow {
+        return Ok(TokenStream::new());
     }
-    if !states.is_empty() {
-        panic!("Not all opening '<' were closed!");
+    let low = styles.iter().find_map(|style| {
+        let ratio = style.contrast_ratio()?;
+        (ratio < contrast.min_ratio).then_some((style, ratio))
+    });
+    let Some((style, ratio)) = low else { return Ok(TokenStream::new()) };
+    let message = format!(
+        "low contrast: '{}' has a contrast ratio of {:.2}, below the minimum of {}",
+        style, ratio, contrast.min_ratio
+    );
+    if contrast.level == Level::Deny {
+        return Err(syn::Error::new(fmt.span(), message));
     }
-    assert!(state == State::default());
-    (out_str, unformatted)
+    let span = fmt.span();
+    Ok(quote_spanned! {span=>
+        #[deprecated(note = #message)]
+        #[allow(non_camel_case_types)]
+        struct low_contrast;
+        let _ = low_contrast;
+    })
 }
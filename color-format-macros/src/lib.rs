@@ -1,17 +1,31 @@
 use proc_macro::{TokenStream, Span};
 use syn::{
-    parse_macro_input, LitStr, Token, punctuated::Punctuated, Expr, token::Comma, Ident,
+    parse_macro_input, Attribute, LitStr, Token, punctuated::Punctuated, Expr, token::Comma, Ident,
     parse::{Parse, ParseStream, Nothing},
 };
 use quote::quote;
 
 mod convert;
-mod codes;
-mod parse;
+mod manifest;
+mod sanitize;
+mod tokens;
+
+/// `#[sanitize]` before the format string escapes control characters in the interpolated values.
+fn parse_sanitize(input: ParseStream) -> syn::Result<bool> {
+    let mut sanitize = false;
+    for attr in input.call(Attribute::parse_outer)? {
+        if !attr.path.is_ident("sanitize") || !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(attr, "expected #[sanitize]"));
+        }
+        sanitize = true;
+    }
+    Ok(sanitize)
+}
 
 struct Args<F, P> {
     f: F,
     _f_punc: P,
+    sanitize: bool,
     fmt_str: LitStr,
     fmt_args: Punctuated<Expr, Token![,]>,
 }
@@ -19,6 +33,7 @@ impl<F: Parse, P: Parse> Parse for Args<F, P> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let f = input.parse()?;
         let f_punc = input.parse()?;
+        let sanitize = parse_sanitize(input)?;
         let fmt_str = input.parse()?;
         let fmt_args = if input.peek(Token![,]) {
             input.parse::<Token![,]>().unwrap();
@@ -26,18 +41,17 @@ impl<F: Parse, P: Parse> Parse for Args<F, P> {
         } else {
             Punctuated::new()
         };
-        Ok(Self { f, _f_punc: f_punc, fmt_str, fmt_args })
-        
+        Ok(Self { f, _f_punc: f_punc, sanitize, fmt_str, fmt_args })
     }
 }
 impl<F, P> Args<F, P> {
     fn convert(self, macro_name: &str) -> TokenStream {
-        convert::colored_macro(None, self.fmt_str, self.fmt_args, macro_name)
+        convert::colored_macro(None, self.fmt_str, self.fmt_args, macro_name, self.sanitize)
     }
 }
 struct LnArgs<F, P> {
     f: F,
-    fmt: Option<(P, LitStr, Punctuated<Expr, Token![,]>)>,
+    fmt: Option<(P, bool, LitStr, Punctuated<Expr, Token![,]>)>,
 }
 impl<F: Parse, P: Parse> Parse for LnArgs<F, P> {
     fn parse(input: ParseStream) -> syn::Result<Self> {
@@ -48,6 +62,7 @@ impl<F: Parse, P: Parse> Parse for LnArgs<F, P> {
             if input.is_empty() {
                 None
             } else {
+                let sanitize = parse_sanitize(input)?;
                 let fmt_str = input.parse()?;
                 let fmt_args = if input.is_empty() {
                     Punctuated::new()
@@ -55,7 +70,7 @@ impl<F: Parse, P: Parse> Parse for LnArgs<F, P> {
                     input.parse::<Token![,]>()?;
                     Punctuated::parse_terminated(input)?
                 };
-                Some((f_punc, fmt_str, fmt_args))
+                Some((f_punc, sanitize, fmt_str, fmt_args))
             }
         } else { None };
         Ok(Self { f, fmt })
@@ -63,8 +78,8 @@ impl<F: Parse, P: Parse> Parse for LnArgs<F, P> {
 }
 impl<F, P> LnArgs<F, P> {
     fn convert(self, macro_name: &str) -> TokenStream {
-        if let Some((_, fmt_str, fmt_args)) = self.fmt {
-            convert::colored_macro(None, fmt_str, fmt_args, macro_name)
+        if let Some((_, sanitize, fmt_str, fmt_args)) = self.fmt {
+            convert::colored_macro(None, fmt_str, fmt_args, macro_name, sanitize)
         } else {
             let ident = Ident::new(macro_name, Span::call_site().into());
             quote!{ #ident!() }.into()
@@ -98,18 +113,35 @@ basic_macros!(
 #[proc_macro]
 pub fn cwrite(item: TokenStream) -> TokenStream {
     let args: WriteArgs = parse_macro_input!(item);
-    convert::colored_macro(Some(args.f), args.fmt_str, args.fmt_args, "write")
+    convert::colored_macro(Some(args.f), args.fmt_str, args.fmt_args, "write", args.sanitize)
 }
 
 #[proc_macro]
 pub fn cwriteln(item: TokenStream) -> TokenStream {
     let args: WriteLnArgs = parse_macro_input!(item);
     let macro_name = "writeln";
-    if let Some((_, fmt_str, fmt_args)) = args.fmt {
-        convert::colored_macro(Some(args.f), fmt_str, fmt_args, macro_name)
+    if let Some((_, sanitize, fmt_str, fmt_args)) = args.fmt {
+        convert::colored_macro(Some(args.f), fmt_str, fmt_args, macro_name, sanitize)
     } else {
         let ident = Ident::new(macro_name, Span::call_site().into());
         let f = args.f;
         quote!{ #ident!(#f) }.into()
     }
-}
\ No newline at end of file
+}
+#[proc_macro]
+pub fn cformat_styled(item: TokenStream) -> TokenStream {
+    let args: ColorArgs = parse_macro_input!(item);
+    convert::styled_macro(args.fmt_str, args.fmt_args, args.sanitize)
+}
+
+#[proc_macro]
+pub fn cformat_html(item: TokenStream) -> TokenStream {
+    let args: ColorArgs = parse_macro_input!(item);
+    convert::html_macro(args.fmt_str, args.fmt_args, args.sanitize)
+}
+
+#[proc_macro]
+pub fn cformat_roff(item: TokenStream) -> TokenStream {
+    let args: ColorArgs = parse_macro_input!(item);
+    convert::roff_macro(args.fmt_str, args.fmt_args, args.sanitize)
+}
@@ -0,0 +1,180 @@
+//! Settings from `[package.metadata.color-format]` in the manifest of the crate that invokes the macros.
+//!
+//! String values are tag aliases, tables hold settings:
+//! ```toml
+//! [package.metadata.color-format]
+//! error = "bright-red;bold"
+//!
+//! [package.metadata.color-format.contrast]
+//! min-ratio = 4.5 # the WCAG AA level for normal text
+//! level = "warn"  # or "deny" or "allow"
+//!
+//! [package.metadata.color-format.html]
+//! class-prefix = "cf-"  # CSS classes instead of inline styles
+//! palette = ["#000000", "#cd0000", ...] # the 16 basic colors
+//! foreground = "#e5e5e5"
+//! background = "#000000"
+//!
+//! [package.metadata.color-format.roff]
+//! colors = true # basic colors as groff colors, dropped by default
+//! ```
+//!
+//! Cargo doesn't rebuild a crate when only its `[package.metadata]` changes, so the macros include the manifest
+//! like a source file, see `track`.
+
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::Mutex, time::SystemTime};
+
+use color_format_core::{Aliases, HtmlOptions, RgbColor, RoffOptions};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Settings {
+    pub aliases: Aliases,
+    pub contrast: Contrast,
+    pub html: HtmlOptions,
+    pub roff: RoffOptions,
+}
+
+/// The check of the contrast between foreground and background colors set by a template.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Contrast {
+    pub min_ratio: f64,
+    pub level: Level,
+}
+impl Default for Contrast {
+    fn default() -> Self {
+        Self { min_ratio: 4.5, level: Level::Warn }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// The settings read from a manifest with its modification time at that point.
+type Cached = (Option<SystemTime>, Result<Settings, String>);
+
+/// The proc macro can be loaded once for several crates and for a long time, for example by rust-analyzer,
+/// so the settings are read again when the manifest changes.
+static CACHE: Mutex<Option<HashMap<PathBuf, Cached>>> = Mutex::new(None);
+
+/// The settings of the crate currently being compiled or an error message if they are invalid.
+pub(crate) fn settings() -> Result<Settings, String> {
+    let Some(path) = manifest_path() else { return Ok(Settings::default()) };
+    let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified()).ok();
+    let mut cache = CACHE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    match cache.get(&path) {
+        Some((read_at, settings)) if modified.is_some() && *read_at == modified => settings.clone(),
+        _ => {
+            let settings = read_settings(&path);
+            cache.insert(path, (modified, settings.clone()));
+            settings
+        }
+    }
+}
+
+fn manifest_path() -> Option<PathBuf> {
+    std::env::var_os("CARGO_MANIFEST_DIR").map(|dir| PathBuf::from(dir).join("Cargo.toml"))
+}
+
+/// An `include_bytes!` of the manifest for the expansion of a macro, so that the crate is compiled again
+/// when the settings change.
+pub(crate) fn track() -> TokenStream {
+    let path = manifest_path().filter(|path| path.is_file());
+    let Some(path) = path.as_deref().and_then(Path::to_str) else { return TokenStream::new() };
+    quote! { const _: &[u8] = include_bytes!(#path); }
+}
+
+fn read_settings(path: &PathBuf) -> Result<Settings, String> {
+    let Ok(manifest) = std::fs::read_to_string(path) else { return Ok(Settings::default()) };
+    let manifest: toml::Table = manifest.parse()
+        .map_err(|e| format!("failed to parse {}: {}", path.display(), e))?;
+    let section = ["package", "metadata", "color-format"].into_iter()
+        .try_fold(&manifest, |table, key| table.get(key)?.as_table());
+    let mut settings = Settings::default();
+    for (name, value) in section.into_iter().flatten() {
+        match value {
+            toml::Value::String(commands) => settings.aliases.insert(name, commands).map_err(|e| format!(
+                "invalid alias '{}' in [package.metadata.color-format] of {}: {}", name, path.display(), e.kind
+            ))?,
+            toml::Value::Table(table) if name == "contrast" => settings.contrast = read_contrast(table, path)?,
+            toml::Value::Table(table) if name == "html" => settings.html = read_html(table, path)?,
+            toml::Value::Table(table) if name == "roff" => settings.roff = read_roff(table, path)?,
+            toml::Value::Table(_) => return Err(format!(
+                "unknown settings [package.metadata.color-format.{}] in {}", name, path.display()
+            )),
+            _ => return Err(format!(
+                "alias '{}' in [package.metadata.color-format] of {} must be a string of tags",
+                name, path.display()
+            )),
+        }
+    }
+    Ok(settings)
+}
+
+fn read_contrast(table: &toml::Table, path: &Path) -> Result<Contrast, String> {
+    let mut contrast = Contrast::default();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("min-ratio", toml::Value::Float(ratio)) => contrast.min_ratio = *ratio,
+            ("min-ratio", toml::Value::Integer(ratio)) => contrast.min_ratio = *ratio as f64,
+            ("level", toml::Value::String(level)) => contrast.level = match level.as_str() {
+                "allow" => Level::Allow,
+                "warn" => Level::Warn,
+                "deny" => Level::Deny,
+                _ => return Err(format!(
+                    "contrast level in {} must be \"allow\", \"warn\" or \"deny\", not {:?}", path.display(), level
+                )),
+            },
+            _ => return Err(format!(
+                "invalid setting '{}' in [package.metadata.color-format.contrast] of {}", key, path.display()
+            )),
+        }
+    }
+    Ok(contrast)
+}
+
+fn read_html(table: &toml::Table, path: &Path) -> Result<HtmlOptions, String> {
+    let mut html = HtmlOptions::default();
+    let color = |value: &toml::Value| value.as_str().and_then(RgbColor::from_hex).ok_or_else(|| format!(
+        "colors in [package.metadata.color-format.html] of {} must be strings like \"#ff8000\", not {:?}",
+        path.display(), value
+    ));
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("class-prefix", toml::Value::String(prefix)) => html.class_prefix = Some(prefix.clone()),
+            ("palette", toml::Value::Array(colors)) if colors.len() == 16 => {
+                for (rgb, value) in html.palette.colors.iter_mut().zip(colors) {
+                    *rgb = color(value)?;
+                }
+            }
+            ("palette", _) => return Err(format!(
+                "the palette in [package.metadata.color-format.html] of {} must be a list of 16 colors", path.display()
+            )),
+            ("foreground", value) => html.palette.foreground = color(value)?,
+            ("background", value) => html.palette.background = color(value)?,
+            _ => return Err(format!(
+                "invalid setting '{}' in [package.metadata.color-format.html] of {}", key, path.display()
+            )),
+        }
+    }
+    Ok(html)
+}
+
+fn read_roff(table: &toml::Table, path: &Path) -> Result<RoffOptions, String> {
+    let mut roff = RoffOptions::default();
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("colors", toml::Value::Boolean(colors)) => roff.colors = *colors,
+            _ => return Err(format!(
+                "invalid setting '{}' in [package.metadata.color-format.roff] of {}", key, path.display()
+            )),
+        }
+    }
+    Ok(roff)
+}
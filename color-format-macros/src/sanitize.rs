@@ -0,0 +1,309 @@
+//! Rewrites the placeholders of a format string to named arguments, so that every interpolated value
+//! can be wrapped in `color_format::__private::Untrusted` while widths and precisions stay plain `usize`s.
+//!
+//! All arguments are bound once in a `match`, like `format_args!` itself used to do, because a single
+//! argument can be referenced by several placeholders with and without `!raw`.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Expr, Ident, LitStr, punctuated::Punctuated, Token};
+
+pub(crate) struct Rewriter {
+    /// The expressions bound in the `match`, positional and named arguments followed by captured variables.
+    bindings: Vec<TokenStream>,
+    positional: usize,
+    /// Names of the explicit named arguments and captured variables, with their binding.
+    names: Vec<(String, usize)>,
+    /// The number of arguments given to the macro, the bindings after them are captured variables.
+    explicit: usize,
+    used: Vec<bool>,
+    next_positional: usize,
+    /// The named arguments that are passed to the emitted macros, with their names.
+    args: Vec<(String, TokenStream)>,
+    span: proc_macro2::Span,
+}
+impl Rewriter {
+    pub(crate) fn new(args: &Punctuated<Expr, Token![,]>, fmt: &LitStr) -> syn::Result<Self> {
+        let mut rewriter = Self {
+            bindings: Vec::new(),
+            positional: 0,
+            names: Vec::new(),
+            explicit: 0,
+            used: Vec::new(),
+            next_positional: 0,
+            args: Vec::new(),
+            span: fmt.span(),
+        };
+        for arg in args {
+            match arg {
+                Expr::Assign(assign) => {
+                    let Expr::Path(path) = &*assign.left else {
+                        return Err(syn::Error::new_spanned(&assign.left, "expected an argument name"));
+                    };
+                    let Some(name) = path.path.get_ident() else {
+                        return Err(syn::Error::new_spanned(path, "expected an argument name"));
+                    };
+                    let right = &assign.right;
+                    rewriter.names.push((name.to_string(), rewriter.bindings.len()));
+                    rewriter.bindings.push(quote! { #right });
+                }
+                _ if !rewriter.names.is_empty() => return Err(syn::Error::new_spanned(
+                    arg, "positional arguments cannot follow named arguments"
+                )),
+                _ => {
+                    rewriter.bindings.push(quote! { #arg });
+                    rewriter.positional += 1;
+                }
+            }
+        }
+        rewriter.explicit = rewriter.bindings.len();
+        rewriter.used = vec![false; rewriter.bindings.len()];
+        Ok(rewriter)
+    }
+
+    /// Rewrites a placeholder like `{0:>width$}` to `{__cf_v0:>__cf_c0$}`.
+    pub(crate) fn placeholder(&mut self, placeholder: &str) -> Result<String, String> {
+        let (arg, mut spec, raw) = split(placeholder);
+        // `.*` takes its precision from the next positional argument, before the value
+        if spec.precision == Some(Count::Star) {
+            let binding = self.next_positional()?;
+            spec.precision = Some(Count::Arg(self.count(binding)));
+        }
+        for count in [&mut spec.width, &mut spec.precision].into_iter().flatten() {
+            if let Count::Ref(reference) = count {
+                let binding = self.reference(reference)?;
+                *count = Count::Arg(self.count(binding));
+            }
+        }
+        let binding = match arg.trim() {
+            "" => self.next_positional()?,
+            reference => self.reference(reference)?,
+        };
+        let name = format_ident!("__cf_v{}", self.args.len());
+        let value = self.binding(binding);
+        self.args.push((name.to_string(), if raw {
+            quote! { #name = #value }
+        } else {
+            quote! { #name = ::color_format::__private::Untrusted(#value) }
+        }));
+        let spec = spec.to_string();
+        Ok(if spec.is_empty() { format!("{{{}}}", name) } else { format!("{{{}:{}}}", name, spec) })
+    }
+
+    /// The binding of the value of a placeholder and those of its width and precision, without rewriting it.
+    pub(crate) fn references(&mut self, placeholder: &str) -> Result<(usize, Vec<usize>), String> {
+        let (arg, spec, _) = split(placeholder);
+        let mut counts = Vec::new();
+        if spec.precision == Some(Count::Star) {
+            counts.push(self.next_positional()?);
+        }
+        for count in [&spec.width, &spec.precision].into_iter().flatten() {
+            if let Count::Ref(reference) = count {
+                counts.push(self.reference(reference)?);
+            }
+        }
+        let value = match arg.trim() {
+            "" => self.next_positional()?,
+            reference => self.reference(reference)?,
+        };
+        Ok((value, counts))
+    }
+
+    fn next_positional(&mut self) -> Result<usize, String> {
+        let index = self.next_positional;
+        self.next_positional += 1;
+        self.positional_arg(index)
+    }
+    fn positional_arg(&mut self, index: usize) -> Result<usize, String> {
+        if index >= self.positional {
+            return Err(format!(
+                "invalid reference to positional argument {} ({} arguments were given)", index, self.positional
+            ));
+        }
+        self.used[index] = true;
+        Ok(index)
+    }
+    fn reference(&mut self, reference: &str) -> Result<usize, String> {
+        if let Ok(index) = reference.parse() {
+            return self.positional_arg(index);
+        }
+        let binding = match self.names.iter().find(|(name, _)| name == reference) {
+            Some(&(_, binding)) => binding,
+            None => {
+                // a variable captured from the surrounding scope
+                if syn::parse_str::<Ident>(reference).is_err() {
+                    return Err(format!("invalid argument name '{}'", reference));
+                }
+                let ident = Ident::new(reference, self.span);
+                self.names.push((reference.to_owned(), self.bindings.len()));
+                self.bindings.push(quote! { #ident });
+                self.used.push(false);
+                self.bindings.len() - 1
+            }
+        };
+        self.used[binding] = true;
+        Ok(binding)
+    }
+    fn binding(&self, index: usize) -> Ident {
+        format_ident!("__cf_arg{}", index)
+    }
+    /// Passes a width or precision on as a named `usize` argument and returns its name.
+    fn count(&mut self, binding: usize) -> String {
+        let name = format_ident!("__cf_c{}", self.args.len());
+        let value = self.binding(binding);
+        self.args.push((name.to_string(), quote! { #name = *#value }));
+        name.to_string()
+    }
+
+    /// Whether any placeholder was rewritten.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.args.is_empty()
+    }
+    /// The named arguments that `fmt_str` refers to, it may leave out some of the rewritten placeholders.
+    pub(crate) fn args_for(&self, fmt_str: &str) -> Vec<&TokenStream> {
+        self.args.iter()
+            .filter(|(name, _)| fmt_str.match_indices(name.as_str()).any(|(i, _)| {
+                fmt_str[i + name.len()..].starts_with(['}', ':', '$'])
+            }))
+            .map(|(_, arg)| arg)
+            .collect()
+    }
+
+    /// The arguments of the macro, with the bindings in `hidden` replaced by `color_format::__private::Hidden`
+    /// after evaluating them. Hidden captured variables are passed on as named arguments.
+    pub(crate) fn hidden_args(&self, hidden: &[bool]) -> Vec<TokenStream> {
+        (0..self.bindings.len()).filter_map(|i| {
+            let binding = &self.bindings[i];
+            let value = if hidden[i] {
+                quote! {{ let _ = &(#binding); ::color_format::__private::Hidden }}
+            } else {
+                binding.clone()
+            };
+            if i < self.positional {
+                return Some(value);
+            }
+            if i >= self.explicit && !hidden[i] {
+                return None;
+            }
+            let (name, _) = self.names.iter().find(|(_, b)| *b == i).expect("named bindings have a name");
+            let name = Ident::new(name, self.span);
+            Some(quote! { #name = #value })
+        }).collect()
+    }
+    /// The number of bindings, arguments and captured variables.
+    pub(crate) fn len(&self) -> usize {
+        self.bindings.len()
+    }
+
+    /// Binds all arguments around `body` and checks that every argument was used.
+    pub(crate) fn bind(&self, body: TokenStream) -> syn::Result<TokenStream> {
+        if let Some(unused) = self.used.iter().position(|used| !used) {
+            let message = match self.names.iter().find(|(_, binding)| *binding == unused) {
+                Some((name, _)) => format!("named argument '{}' never used", name),
+                None => format!("argument {} never used", unused),
+            };
+            return Err(syn::Error::new(self.span, message));
+        }
+        let bindings = &self.bindings;
+        let names = (0..bindings.len()).map(|i| self.binding(i));
+        Ok(quote! {
+            match (#(&(#bindings),)*) {
+                #[allow(unused_variables)]
+                (#(#names,)*) => #body
+            }
+        })
+    }
+}
+
+/// The argument, spec and whether a placeholder like `{0:>width$!raw}` ends with `!raw`.
+fn split(placeholder: &str) -> (&str, Spec<'_>, bool) {
+    let inner = &placeholder[1..placeholder.len() - 1];
+    let (arg, spec) = inner.split_once(':').unwrap_or((inner, ""));
+    match spec.strip_suffix("!raw") {
+        Some(spec) => (arg, Spec::parse(spec), true),
+        None => (arg, Spec::parse(spec), false),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Count<'a> {
+    Literal(&'a str),
+    /// An argument like `1$` or `width$`.
+    Ref(&'a str),
+    Star,
+    /// Rewritten to a named argument.
+    Arg(String),
+}
+
+/// A format spec `[[fill]align][sign]['#']['0'][width]['.' precision][type]`.
+struct Spec<'a> {
+    flags: &'a str,
+    width: Option<Count<'a>>,
+    precision: Option<Count<'a>>,
+    ty: &'a str,
+}
+impl<'a> Spec<'a> {
+    fn parse(spec: &'a str) -> Self {
+        let mut chars = spec.char_indices().peekable();
+        let mut flags_end = 0;
+        let align = |c| matches!(c, '<' | '^' | '>');
+        // fill and alignment
+        let mut first = spec.chars();
+        match (first.next(), first.next()) {
+            (Some(fill), Some(c)) if align(c) => { flags_end = fill.len_utf8() + 1; }
+            (Some(c), _) if align(c) => flags_end = 1,
+            _ => (),
+        }
+        while chars.peek().is_some_and(|&(i, _)| i < flags_end) { chars.next(); }
+        for flag in ['+', '-', '#'] {
+            if let Some(&(i, c)) = chars.peek() {
+                if c == flag { chars.next(); flags_end = i + 1; }
+            }
+        }
+        if spec[flags_end..].starts_with('0') && !spec[flags_end..].starts_with("0$") {
+            flags_end += 1;
+        }
+        let rest = &spec[flags_end..];
+        let (width, rest) = Self::count(rest);
+        let (precision, ty) = match rest.strip_prefix('.') {
+            Some(rest) if rest.starts_with('*') => (Some(Count::Star), &rest[1..]),
+            Some(rest) => Self::count(rest),
+            None => (None, rest),
+        };
+        Self { flags: &spec[..flags_end], width, precision, ty }
+    }
+    /// A count is an integer or an argument followed by `$`, an identifier alone is the type like `x` or `e`.
+    fn count(s: &'a str) -> (Option<Count<'a>>, &'a str) {
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        if digits > 0 {
+            let (number, rest) = s.split_at(digits);
+            return match rest.strip_prefix('$') {
+                Some(rest) => (Some(Count::Ref(number)), rest),
+                None => (Some(Count::Literal(number)), rest),
+            };
+        }
+        let end = s.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(s.len());
+        match s[end..].strip_prefix('$') {
+            Some(rest) if end > 0 => (Some(Count::Ref(&s[..end])), rest),
+            _ => (None, s),
+        }
+    }
+}
+impl std::fmt::Display for Spec<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = |count: &Count| match count {
+            Count::Literal(n) => n.to_string(),
+            Count::Ref(name) => format!("{}$", name),
+            Count::Star => "*".to_owned(),
+            Count::Arg(name) => format!("{}$", name),
+        };
+        f.write_str(self.flags)?;
+        if let Some(width) = &self.width {
+            f.write_str(&count(width))?;
+        }
+        if let Some(precision) = &self.precision {
+            write!(f, ".{}", count(precision))?;
+        }
+        f.write_str(self.ty)
+    }
+}
@@ -0,0 +1,45 @@
+//! Expressions that rebuild values of the core types in the expanded code.
+
+use color_format_core::{Cmd, Color, RgbColor, Semantic};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+pub(crate) fn cmd(cmd: &Cmd) -> TokenStream {
+    let variant = match cmd {
+        Cmd::Color { color: c, background } => {
+            let c = color(c);
+            return quote! { ::color_format::Cmd::Color { color: #c, background: #background } };
+        }
+        Cmd::Semantic(s) => {
+            let s = semantic(*s);
+            return quote! { ::color_format::Cmd::Semantic(#s) };
+        }
+        other => format_ident!("{}", format!("{:?}", other)),
+    };
+    quote! { ::color_format::Cmd::#variant }
+}
+
+pub(crate) fn color(color: &Color) -> TokenStream {
+    match color {
+        Color::Normal => quote! { ::color_format::Color::Normal },
+        Color::Basic(basic) => {
+            let base = format_ident!("{}", format!("{:?}", basic.base));
+            let intensity = format_ident!("{}", format!("{:?}", basic.intensity));
+            quote! {
+                ::color_format::Color::Basic(::color_format::BasicColor {
+                    base: ::color_format::BaseColor::#base,
+                    intensity: ::color_format::Intensity::#intensity,
+                })
+            }
+        }
+        Color::Rgb(RgbColor { r, g, b }) => quote! {
+            ::color_format::Color::Rgb(::color_format::RgbColor { r: #r, g: #g, b: #b })
+        },
+    }
+}
+
+
+pub(crate) fn semantic(semantic: Semantic) -> TokenStream {
+    let semantic = format_ident!("{}", format!("{:?}", semantic));
+    quote! { ::color_format::Semantic::#semantic }
+}
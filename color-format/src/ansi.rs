@@ -0,0 +1,79 @@
+//! Splitting text with escape codes into text and escape sequences.
+
+pub(crate) enum Token<'a> {
+    Text(&'a str),
+    /// A control sequence like `ESC[1;31m`, with its parameter and intermediate bytes and the final byte.
+    Csi { params: &'a str, action: char },
+    /// An operating system command like `ESC]8;;https://example.com ESC\`, without the terminator.
+    Osc(&'a str),
+    /// An escape sequence cut off at the end of the text.
+    Incomplete(&'a str),
+}
+
+/// The tokens of a text, escape sequences other than CSI and OSC are left out.
+pub(crate) struct Tokens<'a>(pub &'a str);
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            let s = self.0;
+            match s.find('\x1b') {
+                None if s.is_empty() => return None,
+                None => {
+                    self.0 = "";
+                    return Some(Token::Text(s));
+                }
+                Some(0) => {
+                    let escape = &s[1..];
+                    let (token, rest) = match escape.chars().next() {
+                        Some('[') => match escape[1..].find(|c| !matches!(c, '\x20'..='\x3f')) {
+                            // parameter and intermediate bytes up to the final byte
+                            Some(end) => {
+                                let (params, rest) = escape[1..].split_at(end);
+                                let action = rest.chars().next().expect("found above");
+                                (Token::Csi { params, action }, &rest[action.len_utf8()..])
+                            }
+                            None => (Token::Incomplete(s), ""),
+                        },
+                        // terminated by BEL or ST
+                        Some(']') => match escape.find(['\x07', '\x1b']) {
+                            Some(end) => {
+                                let (osc, rest) = escape[1..].split_at(end - 1);
+                                match rest.strip_prefix('\x07').or_else(|| rest.strip_prefix("\x1b\\")) {
+                                    Some(rest) => (Token::Osc(osc), rest),
+                                    // another escape sequence or the start of ST
+                                    None if rest.len() > 1 => (Token::Osc(osc), rest),
+                                    None => (Token::Incomplete(s), ""),
+                                }
+                            }
+                            None => (Token::Incomplete(s), ""),
+                        },
+                        // other escape sequences are skipped
+                        Some(c) => {
+                            self.0 = &escape[c.len_utf8()..];
+                            continue;
+                        }
+                        None => (Token::Incomplete(s), ""),
+                    };
+                    self.0 = rest;
+                    return Some(token);
+                }
+                Some(start) => {
+                    self.0 = &s[start..];
+                    return Some(Token::Text(&s[..start]));
+                }
+            }
+        }
+    }
+}
+
+/// The parameters of an SGR sequence, `None` if they are not all numbers.
+pub(crate) fn sgr_params(params: &str) -> Option<Vec<u16>> {
+    params.split(';').map(|param| if param.is_empty() { Some(0) } else { param.parse().ok() }).collect()
+}
+
+/// The link set by an OSC 8 sequence `8;params;url`, where an empty url ends the link.
+pub(crate) fn osc_link(osc: &str) -> Option<Option<&str>> {
+    let (_, url) = osc.strip_prefix("8;")?.split_once(';')?;
+    Some((!url.is_empty()).then_some(url))
+}
@@ -1,32 +1,242 @@
 use ::core::sync::atomic::{AtomicBool, Ordering};
-use std::{io::IsTerminal, sync::OnceLock};
+use std::{fmt, io::IsTerminal, process::Command, sync::{Mutex, OnceLock}};
+
+use color_format_core::{Style, Theme};
+
+/// Environment variables that influence whether colors are used.
+const ENV_VARS: [&str; 3] = ["CLICOLOR_FORCE", "CLICOLOR", "NO_COLOR"];
+
+/// Overrides the background detected from `COLORFGBG`, either `light` or `dark`.
+pub const BACKGROUND_VAR: &str = "COLOR_FORMAT_BACKGROUND";
+/// Turns on colorblind-safe colors when set to anything but `0`, see [`set_colorblind`].
+pub const COLORBLIND_VAR: &str = "COLOR_FORMAT_COLORBLIND";
 
 pub struct Config {
-    colorize: bool,
+    colorize: AtomicBool,
+    detection: Mutex<Detection>,
+    recheck: AtomicBool,
     override_set: AtomicBool,
     override_colorized: AtomicBool,
+    theme: Mutex<Option<Theme>>,
+    colorblind_set: AtomicBool,
+    colorblind: AtomicBool,
+    sanitize: AtomicBool,
 }
 impl Config {
     fn find_out() -> Self {
-        let colorize =
-            env_set("CLICOLOR_FORCE") | env_set("CLICOLOR") | (
-                !env_set("NO_COLOR")
-                && std::io::stdout().is_terminal()
-                && std::io::stderr().is_terminal()
-                //&& atty::is(atty::Stream::Stdout)
-                //&& atty::is(atty::Stream::Stderr)
-            );
+        let detection = Detection::capture();
         Self {
-            colorize,
+            colorize: AtomicBool::new(detection.decide().0),
+            detection: Mutex::new(detection),
+            recheck: AtomicBool::new(false),
             override_set: AtomicBool::new(false),
             override_colorized: AtomicBool::new(false),
+            theme: Mutex::new(None),
+            colorblind_set: AtomicBool::new(false),
+            colorblind: AtomicBool::new(false),
+            sanitize: AtomicBool::new(false),
         }
     }
     pub fn colorize(&self) -> bool {
-        if self.override_set.load(Ordering::Relaxed) {
-            self.override_colorized.load(Ordering::Relaxed)
+        if let Some(colorize) = self.override_value() {
+            return colorize;
+        }
+        if self.recheck.load(Ordering::Relaxed) {
+            self.refresh();
+        }
+        self.colorize.load(Ordering::Relaxed)
+    }
+    /// Decides for a single output stream that is a terminal or not, given by `terminal`.
+    ///
+    /// The override and the environment variables are respected like for [`Config::colorize`].
+    pub fn colorize_for(&self, terminal: bool) -> bool {
+        if let Some(colorize) = self.override_value() {
+            return colorize;
+        }
+        self.detected(|detection| detection.decide_for(terminal).0)
+    }
+    fn refresh(&self) {
+        let detection = Detection::capture();
+        let mut current = self.detection.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.colorize.store(detection.decide().0, Ordering::Relaxed);
+        *current = detection;
+    }
+    fn detected<R>(&self, f: impl FnOnce(&Detection) -> R) -> R {
+        if self.recheck.load(Ordering::Relaxed) {
+            self.refresh();
+        }
+        f(&self.detection.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+    fn override_value(&self) -> Option<bool> {
+        self.override_set.load(Ordering::Relaxed)
+            .then(|| self.override_colorized.load(Ordering::Relaxed))
+    }
+}
+
+/// The inputs that were looked at when deciding whether to colorize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Detection {
+    env: Vec<(&'static str, Option<String>)>,
+    stdout_terminal: bool,
+    stderr_terminal: bool,
+    background: Background,
+    colorblind: bool,
+    truecolor: bool,
+}
+impl Detection {
+    fn capture() -> Self {
+        Self {
+            env: ENV_VARS.iter().map(|&name| (name, std::env::var(name).ok())).collect(),
+            stdout_terminal: std::io::stdout().is_terminal(),
+            stderr_terminal: std::io::stderr().is_terminal(),
+            background: Background::detect(),
+            colorblind: std::env::var(COLORBLIND_VAR).is_ok_and(|value| value != "0"),
+            truecolor: std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit"),
+        }
+    }
+    fn env_set(&self, name: &str) -> bool {
+        self.env.iter()
+            .find(|(var, _)| *var == name)
+            .is_some_and(|(_, value)| !matches!(value.as_deref(), Some("0") | None))
+    }
+    fn decide(&self) -> (bool, Reason) {
+        self.decide_for(self.stdout_terminal && self.stderr_terminal)
+    }
+    /// Decides for output that goes to a terminal or not, given by `terminal`.
+    fn decide_for(&self, terminal: bool) -> (bool, Reason) {
+        if self.env_set("CLICOLOR_FORCE") {
+            (true, Reason::Env("CLICOLOR_FORCE"))
+        } else if self.env_set("CLICOLOR") {
+            (true, Reason::Env("CLICOLOR"))
+        } else if self.env_set("NO_COLOR") {
+            (false, Reason::Env("NO_COLOR"))
+        } else if !terminal {
+            (false, Reason::NotATerminal)
         } else {
-            self.colorize
+            (true, Reason::Terminal)
+        }
+    }
+}
+
+/// Whether the terminal has a light or a dark background, see [`background`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Background {
+    Dark,
+    Light,
+    /// Neither `COLOR_FORMAT_BACKGROUND` nor `COLORFGBG` say anything, treated like a dark background.
+    #[default]
+    Unknown,
+}
+impl Background {
+    fn detect() -> Self {
+        std::env::var(BACKGROUND_VAR).ok().and_then(|value| Self::from_name(&value))
+            .or_else(|| std::env::var("COLORFGBG").ok().and_then(|value| Self::from_colorfgbg(&value)))
+            .unwrap_or_default()
+    }
+    /// Parses `light` or `dark`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+    /// Interprets a `COLORFGBG` value like `15;0` or `0;default;15`, the last field is the background.
+    ///
+    /// The palette indices 7 (white) and 9 to 15 (the bright colors except black) are light backgrounds.
+    pub fn from_colorfgbg(value: &str) -> Option<Self> {
+        match value.rsplit(';').next()?.trim().parse::<u8>().ok()? {
+            7 | 9..=15 => Some(Self::Light),
+            0..=6 | 8 => Some(Self::Dark),
+            _ => None,
+        }
+    }
+    /// The built-in theme that is readable on this background.
+    pub fn theme(self) -> Theme {
+        match self {
+            Self::Light => Theme::light(),
+            Self::Dark | Self::Unknown => Theme::dark(),
+        }
+    }
+}
+impl fmt::Display for Background {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// Why colors were turned on or off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// An override was set using [`set_override`].
+    Override,
+    /// The named environment variable decided.
+    Env(&'static str),
+    /// At least one of stdout and stderr is not a terminal.
+    NotATerminal,
+    /// Both stdout and stderr are terminals.
+    Terminal,
+}
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Override => f.write_str("an override was set by the program"),
+            Reason::Env(name) => write!(f, "{} is set", name),
+            Reason::NotATerminal => f.write_str("stdout or stderr is not a terminal"),
+            Reason::Terminal => f.write_str("stdout and stderr are terminals"),
+        }
+    }
+}
+
+/// A structured explanation of the colorization decision, returned by [`explain`].
+///
+/// The [`Display`](fmt::Display) impl produces a multi-line report suited for bug reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The relevant environment variables and their values at detection time.
+    pub env: Vec<(&'static str, Option<String>)>,
+    pub stdout_terminal: bool,
+    pub stderr_terminal: bool,
+    /// Whether detection is rerun on every call, see [`set_recheck`].
+    pub recheck: bool,
+    /// The override set with [`set_override`], if any.
+    pub override_colorize: Option<bool>,
+    /// The decision made from the environment alone.
+    pub detected: bool,
+    /// The decision that is actually used.
+    pub colorize: bool,
+    pub reason: Reason,
+    pub background: Background,
+    /// Whether red and green are replaced, see [`set_colorblind`].
+    pub colorblind: bool,
+    /// Whether `COLORTERM` announces support for rgb colors.
+    pub truecolor: bool,
+}
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn on_off(b: bool) -> &'static str { if b { "on" } else { "off" } }
+        writeln!(f, "color-format: colors {} because {}", on_off(self.colorize), self.reason)?;
+        writeln!(f, "  environment:")?;
+        for (name, value) in &self.env {
+            match value {
+                Some(value) => writeln!(f, "    {}={:?}", name, value)?,
+                None => writeln!(f, "    {} (unset)", name)?,
+            }
+        }
+        writeln!(f, "  stdout is a terminal: {}", self.stdout_terminal)?;
+        writeln!(f, "  stderr is a terminal: {}", self.stderr_terminal)?;
+        writeln!(f, "  detected: {}", on_off(self.detected))?;
+        writeln!(f, "  recheck on every call: {}", self.recheck)?;
+        writeln!(f, "  background: {}", self.background)?;
+        writeln!(f, "  colorblind-safe colors: {}", self.colorblind)?;
+        writeln!(f, "  truecolor: {}", self.truecolor)?;
+        match self.override_colorize {
+            Some(o) => write!(f, "  override: {}", on_off(o)),
+            None => write!(f, "  override: none"),
         }
     }
 }
@@ -37,6 +247,105 @@ pub fn config() -> &'static Config {
     CONFIG.get_or_init(Config::find_out)
 }
 
+/// Explains what the current configuration decided and why.
+pub fn explain() -> Report {
+    let config = config();
+    let colorize = config.colorize();
+    let detection = config.detection.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    let (detected, detected_reason) = detection.decide();
+    let override_colorize = config.override_value();
+    Report {
+        env: detection.env,
+        stdout_terminal: detection.stdout_terminal,
+        stderr_terminal: detection.stderr_terminal,
+        recheck: config.recheck.load(Ordering::Relaxed),
+        override_colorize,
+        detected,
+        colorize,
+        reason: if override_colorize.is_some() { Reason::Override } else { detected_reason },
+        background: detection.background,
+        colorblind: colorblind(),
+        truecolor: detection.truecolor,
+    }
+}
+
+/// Reruns detection, for example after stdout was redirected or the environment changed.
+///
+/// An override set with [`set_override`] still takes precedence.
+pub fn refresh() {
+    config().refresh();
+}
+
+/// When enabled, detection is rerun every time the configuration is queried.
+///
+/// This picks up redirections immediately but makes every colored macro call
+/// read the environment and query the terminal status of stdout and stderr.
+pub fn set_recheck(recheck: bool) {
+    config().recheck.store(recheck, Ordering::Relaxed);
+}
+
+/// Installs the theme that styles semantic tags like `#error<...>` and `#path<...>`.
+pub fn set_theme(theme: Theme) {
+    *config().theme.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(theme);
+}
+/// Goes back to the theme chosen for the [`background`].
+pub fn unset_theme() {
+    *config().theme.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+/// The theme installed with [`set_theme`] or the built-in one for the [`background`].
+pub fn theme() -> Theme {
+    let installed = *config().theme.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    installed.unwrap_or_else(|| background().theme())
+}
+
+/// The terminal background guessed from `COLOR_FORMAT_BACKGROUND` or else `COLORFGBG`.
+///
+/// Like the colorization decision it is detected once, call [`refresh`] after changing the variables.
+pub fn background() -> Background {
+    config().detected(|detection| detection.background)
+}
+
+/// Replaces red and green with orange and blue, for people with deuteranopia or protanopia.
+///
+/// Takes precedence over `COLOR_FORMAT_COLORBLIND`. Rgb values are used if [`truecolor`] is supported,
+/// yellow and blue otherwise.
+pub fn set_colorblind(colorblind: bool) {
+    let config = config();
+    config.colorblind.store(colorblind, Ordering::Relaxed);
+    config.colorblind_set.store(true, Ordering::Relaxed);
+}
+/// Goes back to the decision of `COLOR_FORMAT_COLORBLIND`.
+pub fn unset_colorblind() {
+    config().colorblind_set.store(false, Ordering::Relaxed);
+}
+/// Whether red and green are replaced, see [`set_colorblind`].
+pub fn colorblind() -> bool {
+    let config = config();
+    if config.colorblind_set.load(Ordering::Relaxed) {
+        config.colorblind.load(Ordering::Relaxed)
+    } else {
+        config.detected(|detection| detection.colorblind)
+    }
+}
+/// Whether the terminal supports rgb colors according to `COLORTERM`.
+pub fn truecolor() -> bool {
+    config().detected(|detection| detection.truecolor)
+}
+/// Applies the adjustments of the configuration that are made when formatting, like [`set_colorblind`].
+pub fn adjust(style: Style) -> Style {
+    if colorblind() { style.colorblind_safe(truecolor()) } else { style }
+}
+
+/// Escapes control characters in the values interpolated by all macros, like `#[sanitize]` does for one.
+///
+/// See [`crate::sanitize`], placeholders with `!raw` are still written as they are.
+pub fn set_sanitize(sanitize: bool) {
+    config().sanitize.store(sanitize, Ordering::Relaxed);
+}
+pub fn sanitize() -> bool {
+    config().sanitize.load(Ordering::Relaxed)
+}
+
 pub fn set_override(colorize: bool) {
     let config = config();
     config.override_colorized.store(colorize, Ordering::Relaxed);
@@ -46,6 +355,64 @@ pub fn unset_override() {
     config().override_set.store(false, Ordering::Relaxed);
 }
 
-fn env_set(name: &str) -> bool {
-    !matches!(std::env::var(name).as_ref().map(String::as_str), Ok("0") | Err(_))
+/// Tools with their own color settings that [`apply_to_command_for`] can configure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// Sets `CARGO_TERM_COLOR` to `always` or `never`.
+    Cargo,
+    /// Adds `color.ui=always` or `color.ui=never` to `GIT_CONFIG_PARAMETERS`.
+    Git,
+    /// Sets the variable `var` to `on` or `off`.
+    Custom { var: &'static str, on: &'static str, off: &'static str },
+}
+
+/// Makes a child process follow the current colorization decision by setting
+/// `CLICOLOR_FORCE`, `CLICOLOR`, `FORCE_COLOR` and `NO_COLOR` on `command`.
+pub fn apply_to_command(command: &mut Command) -> &mut Command {
+    apply_to_command_for(command, &[])
+}
+
+/// Like [`apply_to_command`] but also sets the tool-specific variables of `tools`.
+pub fn apply_to_command_for<'c>(command: &'c mut Command, tools: &[Tool]) -> &'c mut Command {
+    let colorize = config().colorize();
+    if colorize {
+        command
+            .env("CLICOLOR_FORCE", "1")
+            .env("CLICOLOR", "1")
+            .env("FORCE_COLOR", "1")
+            .env_remove("NO_COLOR");
+    } else {
+        command
+            .env_remove("CLICOLOR_FORCE")
+            .env("CLICOLOR", "0")
+            .env_remove("FORCE_COLOR")
+            .env("NO_COLOR", "1");
+    }
+    for tool in tools {
+        match *tool {
+            Tool::Cargo => {
+                command.env("CARGO_TERM_COLOR", if colorize { "always" } else { "never" });
+            }
+            Tool::Git => {
+                // the `'key=value'` form, the one with separately quoted key and value needs git 2.31
+                let setting = if colorize { "'color.ui=always'" } else { "'color.ui=never'" };
+                let inherited = command.get_envs()
+                    .find(|(var, _)| *var == "GIT_CONFIG_PARAMETERS")
+                    .map(|(_, value)| value.map(|v| v.to_os_string()))
+                    .unwrap_or_else(|| std::env::var_os("GIT_CONFIG_PARAMETERS"));
+                match inherited {
+                    Some(mut params) if !params.is_empty() => {
+                        params.push(" ");
+                        params.push(setting);
+                        command.env("GIT_CONFIG_PARAMETERS", params);
+                    }
+                    _ => { command.env("GIT_CONFIG_PARAMETERS", setting); }
+                }
+            }
+            Tool::Custom { var, on, off } => {
+                command.env(var, if colorize { on } else { off });
+            }
+        }
+    }
+    command
 }
@@ -0,0 +1,105 @@
+//! Styles from the standard environment variables `LS_COLORS`, `GREP_COLORS` and `GCC_COLORS`.
+//!
+//! All of them are `:`-separated lists of `key=SGR parameters`, for example `di=01;34:ln=01;36`.
+//! Entries that can't be represented as a [`Style`], like `ln=target` in `LS_COLORS`, are skipped.
+//! ```
+//! use color_format::{env_colors::EnvStyles, Style};
+//! let ls = EnvStyles::parse("di=01;34:ln=target:*.rs=33");
+//! assert_eq!(ls.get("di"), Some("bold;blue".parse::<Style>().unwrap()));
+//! assert_eq!(ls.for_file_name("main.rs"), Some("yellow".parse::<Style>().unwrap()));
+//! ```
+
+use std::fmt;
+
+use color_format_core::{SgrError, Style};
+
+/// The parsed entries of one of the supported environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvStyles {
+    entries: Vec<(String, Style)>,
+    flags: Vec<String>,
+    errors: Vec<EnvStyleError>,
+}
+impl EnvStyles {
+    pub fn parse(s: &str) -> Self {
+        let mut styles = Self::default();
+        for entry in s.split(':').map(str::trim).filter(|entry| !entry.is_empty()) {
+            match entry.split_once('=') {
+                Some((key, params)) => match Style::from_sgr(params) {
+                    Ok(style) => styles.entries.push((key.to_owned(), style)),
+                    Err(error) => styles.errors.push(EnvStyleError { key: key.to_owned(), error }),
+                },
+                None => styles.flags.push(entry.to_owned()),
+            }
+        }
+        styles
+    }
+
+    /// Reads and parses the variable `var`, `None` if it is not set.
+    pub fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var).ok().map(|s| Self::parse(&s))
+    }
+
+    /// The entries that were skipped because their style isn't supported.
+    pub fn errors(&self) -> &[EnvStyleError] {
+        &self.errors
+    }
+
+    /// The style for `key`, the last entry wins like in the tools themselves.
+    pub fn get(&self, key: &str) -> Option<Style> {
+        self.entries.iter().rev().find(|(k, _)| k == key).map(|(_, style)| *style)
+    }
+
+    /// Entries without a value, like the `ne` and `rv` capabilities of `GREP_COLORS`.
+    pub fn flags(&self) -> impl Iterator<Item = &str> {
+        self.flags.iter().map(String::as_str)
+    }
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Style)> {
+        self.entries.iter().map(|(key, style)| (key.as_str(), *style))
+    }
+
+    /// The style of the longest matching `*suffix` entry of `LS_COLORS`, for example `*.rs`.
+    pub fn for_file_name(&self, name: &str) -> Option<Style> {
+        self.entries.iter()
+            .filter_map(|(key, style)| Some((key.strip_prefix('*')?, style)))
+            .filter(|(suffix, _)| name.ends_with(suffix))
+            .max_by_key(|(suffix, _)| suffix.len())
+            .map(|(_, style)| *style)
+    }
+}
+
+/// Parses `LS_COLORS`, keys are file types like `di` and `ln` or patterns like `*.rs`.
+pub fn ls_colors() -> Option<EnvStyles> {
+    EnvStyles::from_env("LS_COLORS")
+}
+
+/// Parses `GREP_COLORS`, keys are like `ms` (selected match) and `fn` (file name).
+pub fn grep_colors() -> Option<EnvStyles> {
+    EnvStyles::from_env("GREP_COLORS")
+}
+
+/// Parses `GCC_COLORS`, keys are like `error`, `warning`, `note` and `locus`.
+pub fn gcc_colors() -> Option<EnvStyles> {
+    EnvStyles::from_env("GCC_COLORS")
+}
+
+/// An entry with SGR parameters that can't be represented as a [`Style`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvStyleError {
+    pub key: String,
+    pub error: SgrError,
+}
+impl fmt::Display for EnvStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid style for '{}': {}", self.key, self.error)
+    }
+}
+impl std::error::Error for EnvStyleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
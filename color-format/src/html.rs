@@ -0,0 +1,39 @@
+//! Rendering styled text as HTML, at runtime with [`to_html`] and at compile time with `cformat_html!`.
+//!
+//! Every style becomes a `<span>` with inline styles, or with CSS classes if a [`HtmlOptions::class_prefix`]
+//! is set, and links become `<a>` elements if they are [safe](is_safe_link).
+//! The contents of `#secret<...>` tags are masked.
+//! The macro takes its options from `[package.metadata.color-format.html]` in the manifest.
+//! ```
+//! use color_format::{cformat_html, cformat_styled, html::to_html};
+//! let html = cformat_html!("#s;r<error>: {}", "<none>");
+//! assert_eq!(html, r#"<span style="font-weight:bold;color:#cd0000">error</span>: &lt;none&gt;"#);
+//! assert_eq!(to_html(&cformat_styled!("#s;r<error>: {}", "<none>")), html);
+//! ```
+
+use color_format_core::{html::escape_html, Style};
+pub use color_format_core::{html::is_safe_link, HtmlOptions, Palette};
+
+use crate::{AsStyledText, SpanKind};
+
+/// Renders text with inline styles and the xterm palette.
+pub fn to_html(text: &(impl AsStyledText + ?Sized)) -> String {
+    to_html_with(text, &HtmlOptions::default())
+}
+
+/// Renders text like [`to_html`], with the classes of [`HtmlOptions::class_prefix`] and the colors of
+/// [`HtmlOptions::palette`].
+pub fn to_html_with(text: &(impl AsStyledText + ?Sized), options: &HtmlOptions) -> String {
+    let text = text.as_styled_text();
+    let mut out = String::new();
+    let default = Style::default();
+    let mut applied = (&default, None);
+    for span in text.spans().iter().filter(|span| matches!(span.kind, SpanKind::Text | SpanKind::Mask)) {
+        let state = (&span.style, span.link.as_deref());
+        options.transition(applied, state, &mut out);
+        applied = state;
+        escape_html(&span.text, &mut out);
+    }
+    options.transition(applied, (&default, None), &mut out);
+    out
+}
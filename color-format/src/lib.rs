@@ -1,4 +1,28 @@
 pub use color_format_macros::*;
 
 #[cfg(feature = "runtime_color")]
-pub mod config;
\ No newline at end of file
+pub mod config;
+#[cfg(feature = "runtime_color")]
+mod writer;
+#[cfg(feature = "runtime_color")]
+pub use writer::{ColorChoice, ColorWriter, plain, Plain};
+
+mod ansi;
+pub mod html;
+mod styled;
+mod template;
+pub mod env_colors;
+pub mod roff;
+pub mod sanitize;
+pub mod svg;
+pub mod testing;
+pub use color_format_core::{
+    Aliases, BaseColor, BasicColor, Boldness, Cmd, Color, Fallback, Intensity, RgbColor, Semantic, SgrError, Style,
+    TemplateError, TemplateErrorKind, Theme, SECRET_MASK,
+};
+pub use styled::{AsStyledText, Span, SpanKind, StyledText};
+pub use template::{RenderError, Template, Values};
+
+#[doc(hidden)]
+#[path = "private.rs"]
+pub mod __private;
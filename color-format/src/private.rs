@@ -0,0 +1,185 @@
+//! Items used by the code the macros expand to. Not part of the public API.
+
+use std::{cell::Cell, fmt};
+
+use color_format_core::Style;
+
+#[cfg(feature = "runtime_color")]
+use crate::{config::config, writer::{ColorChoice, plain_active}};
+use crate::{sanitize::{untrusted, Filter, HtmlFilter, RoffFilter}, Span, SpanKind, StyledText};
+
+/// Reborrows the writer of `cwrite!` with the autoref of a method call, like `write!` does with `write_fmt`,
+/// so that the writer expression is evaluated once and a `&mut W` binding does not need to be `mut`.
+pub trait AsWriter {
+    fn __cf_writer(&mut self) -> &mut Self { self }
+}
+impl<T: ?Sized> AsWriter for T {}
+
+/// Selects the colorization decision of a writer with autoref specialization:
+/// `(&Probe(&writer)).colorize()` resolves to [`WriterChoice`] if the writer implements
+/// [`ColorChoice`] and to [`GlobalChoice`] otherwise.
+#[cfg(feature = "runtime_color")]
+pub struct Probe<'a, T: ?Sized>(pub &'a T);
+
+#[cfg(feature = "runtime_color")]
+pub trait WriterChoice {
+    fn colorize(&self) -> bool;
+}
+#[cfg(feature = "runtime_color")]
+impl<T: ColorChoice + ?Sized> WriterChoice for Probe<'_, T> {
+    fn colorize(&self) -> bool { self.0.colorize() }
+}
+
+#[cfg(feature = "runtime_color")]
+pub trait GlobalChoice {
+    fn colorize(&self) -> bool;
+}
+#[cfg(feature = "runtime_color")]
+impl<T: ?Sized> GlobalChoice for &Probe<'_, T> {
+    fn colorize(&self) -> bool { colorize() }
+}
+
+/// The global decision, unless a value wrapped in [`crate::plain`] is being formatted.
+#[cfg(feature = "runtime_color")]
+pub fn colorize() -> bool {
+    !plain_active() && config().colorize()
+}
+
+#[cfg(feature = "runtime_color")]
+pub use crate::config::{colorblind, theme};
+
+#[cfg(feature = "runtime_color")]
+pub fn adjust<const N: usize>(styles: [Style; N]) -> [Style; N] {
+    if colorblind() {
+        let truecolor = crate::config::truecolor();
+        styles.map(|style| style.colorblind_safe(truecolor))
+    } else { styles }
+}
+
+/// The escape codes between two segments of a template that uses semantic tags.
+///
+/// Switches from `styles[index - 1]` to `styles[index]`, with the default style before the first and after the last one.
+#[cfg(feature = "runtime_color")]
+pub struct Transition<'a>(pub &'a [Style], pub usize);
+#[cfg(feature = "runtime_color")]
+impl fmt::Display for Transition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Transition(styles, index) = *self;
+        let to = styles.get(index).copied().unwrap_or_default();
+        let from = index.checked_sub(1).map_or_else(Style::default, |i| styles[i]);
+        let mut codes = String::new();
+        to.transition(&from, &mut codes);
+        f.write_str(&codes)
+    }
+}
+
+/// An interpolated value, whose output is escaped by [`Sanitized`] if sanitizing is on.
+pub struct Untrusted<'a, T: ?Sized>(pub &'a T);
+macro_rules! untrusted_fmt {
+    ($($trait: ident),*) => {
+        $(
+            impl<T: fmt::$trait + ?Sized> fmt::$trait for Untrusted<'_, T> {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    untrusted(|| fmt::$trait::fmt(self.0, f))
+                }
+            }
+        )*
+    };
+}
+untrusted_fmt!(Display, Debug, LowerHex, UpperHex, Octal, Binary, LowerExp, UpperExp, Pointer);
+
+/// Takes the place of a value inside of `#secret<...>` in the uncolored variant, it is formatted as nothing.
+#[cfg(feature = "runtime_color")]
+pub struct Hidden;
+macro_rules! hidden_fmt {
+    ($($trait: ident),*) => {
+        $(
+            #[cfg(feature = "runtime_color")]
+            impl fmt::$trait for Hidden {
+                fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+hidden_fmt!(Display, Debug, LowerHex, UpperHex, Octal, Binary, LowerExp, UpperExp, Pointer);
+
+/// The arguments of a macro with interpolated values, `true` if it was invoked with `#[sanitize]`.
+pub struct Sanitized<'a>(pub fmt::Arguments<'a>, pub bool);
+impl fmt::Display for Sanitized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if sanitize(self.1) {
+            fmt::write(&mut Filter(f), self.0)
+        } else {
+            f.write_fmt(self.0)
+        }
+    }
+}
+
+/// The arguments of `cformat_html!`, the interpolated values are escaped for HTML.
+pub struct Html<'a>(pub fmt::Arguments<'a>, pub bool);
+impl fmt::Display for Html<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sanitize = sanitize(self.1);
+        fmt::write(&mut HtmlFilter(f, sanitize), self.0)
+    }
+}
+
+/// The arguments of `cformat_roff!`, the interpolated values are escaped for roff.
+pub struct Roff<'a>(pub fmt::Arguments<'a>, pub bool);
+impl fmt::Display for Roff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sanitize = sanitize(self.1);
+        fmt::write(&mut RoffFilter { f, sanitize, line_start: true }, self.0)
+    }
+}
+
+/// Whether values are sanitized, on request with `#[sanitize]` or for all macros with `config::set_sanitize`.
+pub fn sanitize(requested: bool) -> bool {
+    #[cfg(feature = "runtime_color")]
+    return requested || crate::config::sanitize();
+    #[cfg(not(feature = "runtime_color"))]
+    requested
+}
+
+/// Switches the output of a [`StyledBuilder`] to segment `N` of its styles.
+pub struct Segment<'a>(pub &'a Cell<usize>, pub usize);
+impl fmt::Display for Segment<'_> {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.set(self.1);
+        Ok(())
+    }
+}
+
+/// Collects the output of `cformat_styled!` into the spans of the current [`Segment`].
+pub struct StyledBuilder<'a> {
+    text: StyledText,
+    styles: &'a [Style],
+    segments: &'a [(Option<&'static str>, SpanKind)],
+    current: &'a Cell<usize>,
+}
+impl<'a> StyledBuilder<'a> {
+    pub fn new(
+        styles: &'a [Style], segments: &'a [(Option<&'static str>, SpanKind)], current: &'a Cell<usize>,
+    ) -> Self {
+        Self { text: StyledText::new(), styles, segments, current }
+    }
+    pub fn write_fmt(&mut self, args: fmt::Arguments) {
+        fmt::Write::write_fmt(self, args).expect("a Display implementation returned an error unexpectedly");
+    }
+    pub fn finish(self) -> StyledText {
+        self.text
+    }
+}
+impl fmt::Write for StyledBuilder<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+        let index = self.current.get();
+        let (link, kind) = self.segments[index];
+        self.text.push(Span { style: self.styles[index], text: s.to_owned(), link: link.map(str::to_owned), kind });
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+//! Rendering styled text as roff for man pages, at runtime with [`to_roff`] and at compile time with `cformat_roff!`.
+//!
+//! Bold text becomes `\fB...\fR` and italic or underlined text `\fI...\fR`, colors and links are dropped.
+//! Backslashes, hyphens and `.` or `'` at the start of a line are escaped. The contents of `#secret<...>` tags
+//! are masked. The macro takes its options from `[package.metadata.color-format.roff]` in the manifest.
+//! ```
+//! use color_format::{cformat_roff, cformat_styled, roff::to_roff};
+//! let roff = cformat_roff!("#s<--color> #u<{}>", "WHEN");
+//! assert_eq!(roff, r"\fB\-\-color\fR \fIWHEN\fR");
+//! assert_eq!(to_roff(&cformat_styled!("#s<--color> #u<{}>", "WHEN")), roff);
+//! ```
+
+use color_format_core::{roff::escape_roff, Style};
+pub use color_format_core::RoffOptions;
+
+use crate::{AsStyledText, SpanKind};
+
+/// Renders text with fonts and without colors.
+pub fn to_roff(text: &(impl AsStyledText + ?Sized)) -> String {
+    to_roff_with(text, &RoffOptions::default())
+}
+
+/// Renders text like [`to_roff`], with basic foreground colors as groff colors like `\m[red]` if
+/// [`RoffOptions::colors`] is set.
+/// ```
+/// use color_format::roff::{to_roff_with, RoffOptions};
+/// let options = RoffOptions { colors: true };
+/// assert_eq!(to_roff_with("\x1b[31mred\x1b[0m", &options), r"\m[red]red\m[]");
+/// ```
+pub fn to_roff_with(text: &(impl AsStyledText + ?Sized), options: &RoffOptions) -> String {
+    let text = text.as_styled_text();
+    let mut out = String::new();
+    let mut applied = Style::default();
+    for span in text.spans().iter().filter(|span| matches!(span.kind, SpanKind::Text | SpanKind::Mask)) {
+        options.transition(&applied, &span.style, &mut out);
+        applied = span.style;
+        escape_roff(&span.text, out.is_empty() || out.ends_with('\n'), &mut out);
+    }
+    options.transition(&applied, &Style::default(), &mut out);
+    out
+}
@@ -0,0 +1,105 @@
+//! Escaping of control characters in interpolated values, against terminal injection.
+//!
+//! A value like a file name can contain `\x1b]` or `\x1b[` sequences that change the terminal.
+//! With `cformat!(#[sanitize] "...")` or [`crate::config::set_sanitize`] they are escaped,
+//! while the escape codes of the tags themselves are kept. `{:!raw}` opts out for a single placeholder.
+//! ```
+//! use color_format::cformat;
+//! let name = "evil\x1b]0;title\x07";
+//! assert_eq!(cformat!(#[sanitize] "{}", name), "evil\\u{1b}]0;title\\u{7}");
+//! assert_eq!(cformat!(#[sanitize] "{:!raw}", name), name);
+//! ```
+
+use std::{borrow::Cow, cell::Cell, fmt};
+
+use color_format_core::{html::escape_html, roff::escape_roff};
+
+/// Escapes C0 and C1 control characters including ESC and DEL like `\u{1b}`, but not `\n` and `\t`.
+pub fn escape_control_chars(s: &str) -> Cow<'_, str> {
+    let escaped = |c: char| c.is_control() && c != '\n' && c != '\t';
+    if !s.chars().any(escaped) {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        if escaped(c) {
+            out.extend(c.escape_default());
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+thread_local! {
+    static UNTRUSTED_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks the output written while `f` runs as untrusted.
+pub(crate) fn untrusted<R>(f: impl FnOnce() -> R) -> R {
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            UNTRUSTED_DEPTH.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+    UNTRUSTED_DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let _guard = Guard;
+    f()
+}
+
+/// Escapes what is written while an untrusted value is being formatted.
+pub(crate) struct Filter<'a, 'b>(pub &'a mut fmt::Formatter<'b>);
+impl fmt::Write for Filter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if UNTRUSTED_DEPTH.with(Cell::get) > 0 {
+            self.0.write_str(&escape_control_chars(s))
+        } else {
+            self.0.write_str(s)
+        }
+    }
+}
+
+/// Escapes untrusted values for HTML, and their control characters if `sanitize` is set.
+pub(crate) struct HtmlFilter<'a, 'b>(pub &'a mut fmt::Formatter<'b>, pub bool);
+impl fmt::Write for HtmlFilter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if UNTRUSTED_DEPTH.with(Cell::get) > 0 {
+            let s = if self.1 { escape_control_chars(s) } else { Cow::Borrowed(s) };
+            let mut escaped = String::with_capacity(s.len());
+            escape_html(&s, &mut escaped);
+            self.0.write_str(&escaped)
+        } else {
+            self.0.write_str(s)
+        }
+    }
+}
+
+/// Escapes untrusted values for roff, and their control characters if `sanitize` is set.
+///
+/// Also escapes a `.` or `'` at the start of the text after a value ending in a newline.
+pub(crate) struct RoffFilter<'a, 'b> {
+    pub f: &'a mut fmt::Formatter<'b>,
+    pub sanitize: bool,
+    pub line_start: bool,
+}
+impl fmt::Write for RoffFilter<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+        if UNTRUSTED_DEPTH.with(Cell::get) > 0 {
+            let s = if self.sanitize { escape_control_chars(s) } else { Cow::Borrowed(s) };
+            let mut escaped = String::with_capacity(s.len());
+            escape_roff(&s, self.line_start, &mut escaped);
+            self.f.write_str(&escaped)?;
+        } else {
+            if self.line_start && s.starts_with(['.', '\'']) {
+                self.f.write_str("\\&")?;
+            }
+            self.f.write_str(s)?;
+        }
+        self.line_start = s.ends_with('\n');
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Formatted text as spans of styled text, see [`cformat_styled!`](crate::cformat_styled).
+
+use std::{borrow::Cow, fmt};
+
+use color_format_core::{codes::add_hyperlink, Boldness, Color, Style};
+
+use crate::ansi::{osc_link, sgr_params, Token, Tokens};
+
+/// Formatted text that keeps its styles, to be rendered later.
+///
+/// ```
+/// use color_format::{cformat_styled, Style};
+/// let text = cformat_styled!("#u(link='https://example.com')<{}> done", "docs");
+/// assert_eq!(text.spans()[0].link.as_deref(), Some("https://example.com"));
+/// assert_eq!(text.to_plain(), "docs done");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledText {
+    spans: Vec<Span>,
+}
+
+/// Text in one style, adjacent spans differ in their style, link or kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+    pub link: Option<String>,
+    pub kind: SpanKind,
+}
+
+/// When a [`Span`] is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// Always.
+    #[default]
+    Text,
+    /// Only without colors, from `#tag(fallback="...")<...>` and the markers of semantic tags.
+    Fallback,
+    /// The content of a `#secret<...>` tag, only with colors.
+    Secret,
+    /// Stands in for the content of a `#secret<...>` tag without colors.
+    Mask,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Self { style, text: text.into(), link: None, kind: SpanKind::Text }
+    }
+
+    /// Whether the span is shown in the output with or without colors.
+    pub fn is_visible(&self, colorize: bool) -> bool {
+        match self.kind {
+            SpanKind::Text => true,
+            SpanKind::Secret => colorize,
+            SpanKind::Fallback | SpanKind::Mask => !colorize,
+        }
+    }
+}
+
+impl StyledText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses text with escape codes, like the output of `cformat!` or of other programs.
+    ///
+    /// SGR sequences set the style, including 256 colors (`38;5;N`) and rgb colors (`38;2;R;G;B`),
+    /// and OSC 8 sequences set the link. Unsupported SGR codes and all other escape sequences are left out.
+    /// ```
+    /// use color_format::StyledText;
+    /// let text = StyledText::from_ansi("\x1b[1;38;5;196mfailed\x1b[0m: x");
+    /// assert_eq!(text.spans()[0].style.to_string(), "bold;rgb(255,0,0)");
+    /// assert_eq!(text.to_plain(), "failed: x");
+    /// ```
+    pub fn from_ansi(s: &str) -> Self {
+        let mut text = Self::new();
+        let mut style = Style::default();
+        let mut link: Option<String> = None;
+        for token in Tokens(s) {
+            match token {
+                Token::Text(s) => text.push(Span { link: link.clone(), ..Span::new(s, style) }),
+                Token::Csi { params, action: 'm' } => if let Some(params) = sgr_params(params) {
+                    style.apply_sgr_codes_lossy(&params);
+                }
+                Token::Osc(osc) => if let Some(url) = osc_link(osc) {
+                    link = url.map(str::to_owned);
+                }
+                Token::Csi { .. } | Token::Incomplete(_) => (),
+            }
+        }
+        text
+    }
+
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+
+    pub fn into_spans(self) -> Vec<Span> {
+        self.spans
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Appends `span`, merging it into the last span if only the text differs. Empty spans are left out.
+    pub fn push(&mut self, span: Span) {
+        if span.text.is_empty() {
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(last) if (last.style, &last.link, last.kind) == (span.style, &span.link, span.kind) => {
+                last.text.push_str(&span.text);
+            }
+            _ => self.spans.push(span),
+        }
+    }
+
+    /// Renders the text with escape codes, like `cformat!` does with colors on.
+    ///
+    /// Links are written as OSC 8 hyperlinks.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let mut applied = Style::default();
+        let mut link = None;
+        for span in self.spans.iter().filter(|span| span.is_visible(true)) {
+            span.style.transition(&applied, &mut out);
+            applied = span.style;
+            if span.link.as_deref() != link {
+                link = span.link.as_deref();
+                add_hyperlink(&mut out, link);
+            }
+            out.push_str(&span.text);
+        }
+        Style::default().transition(&applied, &mut out);
+        if link.is_some() {
+            add_hyperlink(&mut out, None);
+        }
+        out
+    }
+
+    /// Writes the text as it is shown with colors in the tag syntax, for example `#r<red#u;g<both>>`.
+    ///
+    /// Attributes that last longer get the outer tags and a tag stays open when its style comes back after inner ones.
+    /// Links become `(link='...')` attributes and `#`, `>` and braces are escaped, so the result is a valid template.
+    pub fn to_tags(&self) -> String {
+        let spans = self.spans.iter()
+            .filter(|span| span.is_visible(true))
+            .map(|span| (slots(&span.style, span.link.as_deref()), span.text.as_str()))
+            .collect::<Vec<_>>();
+        let mut out = String::new();
+        // the attributes inside of each open tag and the slots that the tag sets, starting outside of all tags
+        let mut open: Vec<(Slots, Vec<usize>)> = vec![Default::default()];
+        for (i, (state, text)) in spans.iter().enumerate() {
+            let keep = match open.iter().rposition(|(slots, _)| slots == state) {
+                Some(index) => index + 1,
+                None => {
+                    let rest = &spans[i + 1..];
+                    // the first open tag that the text cannot stay inside of
+                    open.iter().position(|(slots, own)| {
+                        let extends = |other: &Slots| (0..SLOTS).all(|k| slots[k] == other[k] || other[k].is_some());
+                        let contains = own.iter().all(|&k| slots[k] == state[k]);
+                        let returns = || rest.iter()
+                            .take_while(|(next, _)| extends(next))
+                            .any(|(next, _)| next == slots);
+                        !(extends(state) && (contains || returns()))
+                    }).unwrap_or(open.len())
+                }
+            };
+            for _ in keep..open.len() {
+                out.push('>');
+            }
+            open.truncate(keep);
+            // open tags for the changed attributes, the ones that last the longest first
+            let outer = open.last().expect("the outermost entry is never closed").0;
+            let mut changed = (0..SLOTS)
+                .filter(|&k| outer[k] != state[k])
+                .map(|k| (spans[i..].iter().take_while(|(next, _)| next[k] == state[k]).count(), k))
+                .collect::<Vec<_>>();
+            changed.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            for group in changed.chunk_by(|a, b| a.0 == b.0) {
+                let mut slots = open.last().expect("the outermost entry is never closed").0;
+                let mut tag = Slots::default();
+                for &(_, k) in group {
+                    slots[k] = state[k];
+                    tag[k] = state[k];
+                }
+                out.push('#');
+                write_tag(&tag, &mut out);
+                out.push('<');
+                open.push((slots, group.iter().map(|&(_, k)| k).collect()));
+            }
+            for c in text.chars() {
+                match c {
+                    '#' => out.push_str("##"),
+                    '>' => out.push_str("#>"),
+                    '{' => out.push_str("{{"),
+                    '}' => out.push_str("}}"),
+                    c => out.push(c),
+                }
+            }
+        }
+        out.extend(open[1..].iter().map(|_| '>'));
+        out
+    }
+
+    /// Renders the text without escape codes, like `cformat!` does with colors off.
+    pub fn to_plain(&self) -> String {
+        self.spans.iter().filter(|span| span.is_visible(false)).map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// The attributes of a span for [`StyledText::to_tags`]: boldness, colors, the six flags and the link.
+type Slots<'a> = [Option<Attr<'a>>; SLOTS];
+const SLOTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attr<'a> {
+    Boldness(Boldness),
+    Color(Color),
+    Flag,
+    Link(&'a str),
+}
+
+fn slots<'a>(style: &Style, link: Option<&'a str>) -> Slots<'a> {
+    let color = |color| (color != Color::Normal).then_some(Attr::Color(color));
+    let flag = |set: bool| set.then_some(Attr::Flag);
+    [
+        (style.boldness != Boldness::Normal).then_some(Attr::Boldness(style.boldness)),
+        color(style.fg_color),
+        color(style.bg_color),
+        flag(style.italic),
+        flag(style.underline),
+        flag(style.blink),
+        flag(style.reverse),
+        flag(style.conceal),
+        flag(style.strike),
+        link.map(Attr::Link),
+    ]
+}
+
+/// Writes the commands and the link attribute of a tag that sets `tag`.
+fn write_tag(tag: &Slots, out: &mut String) {
+    let mut style = Style::default();
+    let mut link = None;
+    for (k, attr) in tag.iter().enumerate() {
+        match (k, *attr) {
+            (_, Some(Attr::Boldness(boldness))) => style.boldness = boldness,
+            (1, Some(Attr::Color(color))) => style.fg_color = color,
+            (_, Some(Attr::Color(color))) => style.bg_color = color,
+            (_, Some(Attr::Link(url))) => link = Some(url),
+            (k, Some(Attr::Flag)) => *[
+                &mut style.italic, &mut style.underline, &mut style.blink,
+                &mut style.reverse, &mut style.conceal, &mut style.strike,
+            ][k - 3] = true,
+            (_, None) => (),
+        }
+    }
+    out.push_str(&format!("{:#}", style));
+    if let Some(url) = link {
+        let quote = if url.contains('\'') { '"' } else { '\'' };
+        out.push_str(&format!("(link={0}{1}{0})", quote, url));
+    }
+}
+
+impl fmt::Display for StyledText {
+    /// Renders with escape codes according to the global configuration.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "runtime_color")]
+        let colorize = crate::__private::colorize();
+        #[cfg(not(feature = "runtime_color"))]
+        let colorize = true;
+        f.write_str(&if colorize { self.to_ansi() } else { self.to_plain() })
+    }
+}
+impl FromIterator<Span> for StyledText {
+    fn from_iter<I: IntoIterator<Item = Span>>(iter: I) -> Self {
+        let mut text = Self::new();
+        iter.into_iter().for_each(|span| text.push(span));
+        text
+    }
+}
+
+/// Text with styles: a [`StyledText`] or a string with escape codes, which is parsed with [`StyledText::from_ansi`].
+pub trait AsStyledText {
+    fn as_styled_text(&self) -> Cow<'_, StyledText>;
+}
+impl AsStyledText for StyledText {
+    fn as_styled_text(&self) -> Cow<'_, StyledText> { Cow::Borrowed(self) }
+}
+impl AsStyledText for str {
+    fn as_styled_text(&self) -> Cow<'_, StyledText> { Cow::Owned(StyledText::from_ansi(self)) }
+}
+impl AsStyledText for String {
+    fn as_styled_text(&self) -> Cow<'_, StyledText> { self.as_str().as_styled_text() }
+}
@@ -0,0 +1,165 @@
+//! Rendering colored output as an SVG "terminal screenshot" for documentation.
+//!
+//! The text is laid out like a terminal would, see [`Screen`], and drawn with a monospace font,
+//! rectangles for background colors and optionally a window frame. The contents of `#secret<...>` tags are masked.
+//! ```
+//! use color_format::{cformat_styled, svg::{to_svg, SvgOptions}};
+//! let svg = to_svg(&cformat_styled!("#g<ok> 3 tests"), &SvgOptions { columns: 20, ..SvgOptions::default() });
+//! assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+//! assert!(svg.contains(">ok</tspan>"));
+//! ```
+
+use color_format_core::{html::{escape_html, is_safe_link}, Boldness, Palette, RgbColor, Style};
+
+use crate::{testing::{Cell, Screen}, AsStyledText, Span, SpanKind, StyledText};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgOptions {
+    pub palette: Palette,
+    /// The CSS font family, should be monospace.
+    pub font_family: String,
+    /// In pixels, characters are assumed to be 0.6 times as wide.
+    pub font_size: f64,
+    /// The width of the terminal, longer lines wrap. At least one column is used.
+    pub columns: usize,
+    /// Draw a window frame with a title bar around the text.
+    pub frame: bool,
+    /// The title shown in the frame.
+    pub title: Option<String>,
+}
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            font_family: "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace".to_owned(),
+            font_size: 14.0,
+            columns: 80,
+            frame: true,
+            title: None,
+        }
+    }
+}
+
+const PADDING: f64 = 12.0;
+const TITLE_BAR: f64 = 28.0;
+
+pub fn to_svg(text: &(impl AsStyledText + ?Sized), options: &SvgOptions) -> String {
+    let text = text.as_styled_text();
+    // masked like in HTML
+    let shown = text.spans().iter()
+        .filter(|span| matches!(span.kind, SpanKind::Text | SpanKind::Mask))
+        .map(|span| Span { kind: SpanKind::Text, ..span.clone() })
+        .collect::<StyledText>();
+    let columns = options.columns.max(1);
+    let mut screen = Screen::new(columns);
+    screen.write_str(&shown.to_ansi());
+    let rows = (0..screen.height()).rposition(|row| !screen.row(row).is_empty()).map_or(0, |row| row + 1);
+
+    let palette = &options.palette;
+    let char_width = options.font_size * 0.6;
+    let line_height = options.font_size * 1.4;
+    let top = if options.frame { TITLE_BAR } else { 0.0 } + PADDING;
+    let width = columns as f64 * char_width + 2.0 * PADDING;
+    let height = top + rows as f64 * line_height + PADDING;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.1}\" height=\"{1:.1}\" viewBox=\"0 0 {0:.1} {1:.1}\">\n",
+        width, height,
+    );
+    out.push_str(&format!(
+        "<rect width=\"100%\" height=\"100%\" rx=\"{}\" fill=\"{}\"/>\n",
+        if options.frame { 6 } else { 0 }, palette.background.to_hex(),
+    ));
+    if options.frame {
+        for (i, color) in ["#ff5f56", "#ffbd2e", "#27c93f"].into_iter().enumerate() {
+            out.push_str(&format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"6\" fill=\"{}\"/>\n",
+                PADDING + 6.0 + i as f64 * 20.0, TITLE_BAR / 2.0, color,
+            ));
+        }
+        if let Some(title) = &options.title {
+            out.push_str(&format!(
+                "<text x=\"50%\" y=\"{}\" text-anchor=\"middle\" font-family=\"{}\" font-size=\"{}\" fill=\"{}\">",
+                TITLE_BAR / 2.0 + 4.0, escape(&options.font_family), options.font_size * 0.9,
+                palette.foreground.to_hex(),
+            ));
+            out.push_str(&escape(title));
+            out.push_str("</text>\n");
+        }
+    }
+    out.push_str(&format!(
+        "<g font-family=\"{}\" font-size=\"{}\" xml:space=\"preserve\">\n",
+        escape(&options.font_family), options.font_size,
+    ));
+    for row in 0..rows {
+        let y = top + row as f64 * line_height;
+        let cells = screen.row(row);
+        // runs of cells in the same style and with the same link
+        let mut runs: Vec<(usize, &[Cell])> = Vec::new();
+        let mut start = 0;
+        for end in 1..=cells.len() {
+            if end == cells.len() || (cells[end].style, &cells[end].link) != (cells[start].style, &cells[start].link) {
+                runs.push((start, &cells[start..end]));
+                start = end;
+            }
+        }
+        for &(col, run) in &runs {
+            let (_, bg) = colors(&run[0].style, palette);
+            if let Some(bg) = bg {
+                out.push_str(&format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\n",
+                    PADDING + col as f64 * char_width, y, run.len() as f64 * char_width, line_height, bg.to_hex(),
+                ));
+            }
+        }
+        out.push_str(&format!("<text y=\"{:.1}\">", y + line_height * 0.75));
+        for &(col, run) in &runs {
+            let style = &run[0].style;
+            if style.conceal || run.iter().all(|cell| cell.ch == ' ') {
+                continue;
+            }
+            let (fg, _) = colors(style, palette);
+            let mut attributes = format!(" x=\"{:.1}\" fill=\"{}\"", PADDING + col as f64 * char_width, fg.to_hex());
+            match style.boldness {
+                Boldness::Normal => (),
+                Boldness::Bold => attributes.push_str(" font-weight=\"bold\""),
+                Boldness::Faint => attributes.push_str(" opacity=\"0.6\""),
+            }
+            if style.italic {
+                attributes.push_str(" font-style=\"italic\"");
+            }
+            let decorations = [(style.underline, "underline"), (style.strike, "line-through")]
+                .into_iter()
+                .filter_map(|(set, decoration)| set.then_some(decoration))
+                .collect::<Vec<_>>();
+            if !decorations.is_empty() {
+                attributes.push_str(&format!(" text-decoration=\"{}\"", decorations.join(" ")));
+            }
+            let text = run.iter().map(|cell| cell.ch).collect::<String>();
+            let tspan = format!("<tspan{}>{}</tspan>", attributes, escape(&text));
+            match run[0].link.as_deref().filter(|url| is_safe_link(url)) {
+                Some(url) => out.push_str(&format!("<a href=\"{}\">{}</a>", escape(url), tspan)),
+                None => out.push_str(&tspan),
+            }
+        }
+        out.push_str("</text>\n");
+    }
+    out.push_str("</g>\n</svg>\n");
+    out
+}
+
+/// The text color and the background color if it is not the default, swapped for reversed text.
+fn colors(style: &Style, palette: &Palette) -> (RgbColor, Option<RgbColor>) {
+    let fg = palette.rgb(style.fg_color);
+    let bg = palette.rgb(style.bg_color);
+    if style.reverse {
+        (bg.unwrap_or(palette.background), Some(fg.unwrap_or(palette.foreground)))
+    } else {
+        (fg.unwrap_or(palette.foreground), bg)
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    escape_html(s, &mut escaped);
+    escaped
+}
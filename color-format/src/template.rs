@@ -0,0 +1,308 @@
+use std::{
+    borrow::Borrow, collections::{BTreeMap, HashMap}, fmt::{self, Display, Write}, hash::{BuildHasher, Hash},
+};
+
+use color_format_core::{
+    codes::add_hyperlink, Aliases, Cmd, Semantic, StringParser, StringPart, Style, TemplateError, TemplateErrorKind,
+    Theme, SECRET_MASK,
+};
+
+/// A template parsed at runtime, using the same `#tag<...>` syntax as the macros.
+///
+/// Values are inserted with named placeholders like `{name}`, literal braces are written as `{{` and `}}`.
+/// ```
+/// use color_format::Template;
+/// let template = Template::parse("#g<{name}> finished in #s<{time}>s").unwrap();
+/// let values = [("name", "build"), ("time", "1.5")].into_iter().collect::<std::collections::HashMap<_, _>>();
+/// assert_eq!(template.render_with(&values, false).unwrap(), "build finished in 1.5s");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// The commands of all open tags, resolved to a style when rendering because of semantic tags.
+    segments: Vec<(Vec<Cmd>, Piece)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Value { name: String, offset: usize },
+    /// Only written when colors are off.
+    Fallback(String),
+    /// The fallback of the theme for a semantic tag, `true` for the text after the tag.
+    Marker(Semantic, bool),
+    /// Stands in for the content of a `#secret<...>` tag when colors are off.
+    Mask,
+    /// The hyperlink of the following text, only written when colors are on.
+    Link(Option<String>),
+}
+
+/// What is written around a tag when colors are off.
+enum TagFallback {
+    Explicit { before: String, after: String },
+    Semantic(Vec<Semantic>),
+}
+impl TagFallback {
+    fn push(&self, after: bool, style: &[Cmd], segments: &mut Vec<(Vec<Cmd>, Piece)>) {
+        match self {
+            TagFallback::Explicit { before, after: end } => {
+                let text = if after { end } else { before };
+                if !text.is_empty() {
+                    segments.push((style.to_vec(), Piece::Fallback(text.clone())));
+                }
+            }
+            TagFallback::Semantic(semantics) => {
+                let marker = |&semantic| (style.to_vec(), Piece::Marker(semantic, after));
+                if after {
+                    segments.extend(semantics.iter().rev().map(marker));
+                } else {
+                    segments.extend(semantics.iter().map(marker));
+                }
+            }
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Self, TemplateError> {
+        Self::parse_parts(StringParser::new(s))
+    }
+
+    /// Parses the template and also resolves the custom tags defined in `aliases`.
+    pub fn parse_with_aliases(s: &str, aliases: &Aliases) -> Result<Self, TemplateError> {
+        Self::parse_parts(StringParser::with_aliases(s, aliases))
+    }
+
+    fn parse_parts(parser: StringParser) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut states = Vec::new();
+        let mut style = Vec::new();
+        let mut fallbacks = Vec::new();
+        let mut fallback_pending = false;
+        let mut links = Vec::new();
+        let mut link = None;
+        for part in parser {
+            let (offset, part) = part?;
+            if !matches!(part, StringPart::Attributes(_)) && std::mem::take(&mut fallback_pending) {
+                // fallbacks belong to the surrounding tags, so a secret tag keeps its own
+                let outer = &style[..*states.last().expect("a tag was opened")];
+                let fallback: &TagFallback = fallbacks.last().expect("a tag was opened");
+                fallback.push(false, outer, &mut segments);
+                if style[outer.len()..].contains(&Cmd::Secret) {
+                    segments.push((outer.to_vec(), Piece::Mask));
+                }
+            }
+            match part {
+                StringPart::String(text) => {
+                    let text = unescape_braces(text, offset)?;
+                    match segments.last_mut() {
+                        Some((last_style, Piece::Text(last))) if *last_style == style => last.push_str(&text),
+                        _ => segments.push((style.clone(), Piece::Text(text))),
+                    }
+                }
+                StringPart::Placeholder(placeholder) => {
+                    let name = placeholder[1..placeholder.len() - 1].trim();
+                    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {
+                        return Err(TemplateError {
+                            kind: TemplateErrorKind::InvalidPlaceholder(name.to_owned()),
+                            span: offset..offset + placeholder.len(),
+                        });
+                    }
+                    segments.push((style.clone(), Piece::Value { name: name.to_owned(), offset }));
+                }
+                StringPart::StartCmd(cmds) => {
+                    states.push(style.len());
+                    links.push(link.clone());
+                    fallbacks.push(TagFallback::Semantic(cmds.iter()
+                        .filter_map(|cmd| match cmd { Cmd::Semantic(s) => Some(*s), _ => None })
+                        .collect()));
+                    fallback_pending = true;
+                    style.extend(cmds);
+                }
+                StringPart::Attributes(attributes) => {
+                    if let Some(fallback) = attributes.fallback {
+                        *fallbacks.last_mut().expect("attributes follow their tag") = TagFallback::Explicit {
+                            before: fallback.before.to_owned(),
+                            after: fallback.after.to_owned(),
+                        };
+                    }
+                    if let Some(url) = attributes.link {
+                        link = Some(url.to_owned());
+                        segments.push((style.clone(), Piece::Link(link.clone())));
+                    }
+                }
+                StringPart::EndCmd => {
+                    style.truncate(states.pop().expect("parser checks that tags are balanced"));
+                    fallbacks.pop().expect("parser checks that tags are balanced").push(true, &style, &mut segments);
+                    let outer = links.pop().expect("parser checks that tags are balanced");
+                    if outer != link {
+                        link = outer;
+                        segments.push((style.clone(), Piece::Link(link.clone())));
+                    }
+                }
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    /// The names of all placeholders in the order they appear.
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|(_, piece)| match piece {
+            Piece::Value { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Renders the template, colorizing it according to the global configuration.
+    #[cfg(feature = "runtime_color")]
+    pub fn render<V: Values + ?Sized>(&self, values: &V) -> Result<String, RenderError> {
+        self.render_with(values, crate::__private::colorize())
+    }
+
+    /// Renders the template, with escape codes only if `colorize` is set.
+    pub fn render_with<V: Values + ?Sized>(&self, values: &V, colorize: bool) -> Result<String, RenderError> {
+        let mut out = String::new();
+        self.write_to(&mut out, values, colorize)?;
+        Ok(out)
+    }
+
+    /// Writes the rendered template to `out`, semantic tags use the theme from the configuration.
+    ///
+    /// Colors are also adjusted by the configuration, for example with [`crate::config::set_colorblind`].
+    pub fn write_to<V: Values + ?Sized>(&self, out: &mut impl Write, values: &V, colorize: bool)
+    -> Result<(), RenderError> {
+        #[cfg(feature = "runtime_color")]
+        return self.write_styled(out, values, &crate::config::theme(), colorize.then_some(crate::config::adjust));
+        #[cfg(not(feature = "runtime_color"))]
+        self.write_themed(out, values, colorize.then(Theme::default).as_ref())
+    }
+
+    /// Writes the rendered template to `out`, with escape codes only if a `theme` is given.
+    ///
+    /// Without a theme the fallbacks of the default theme are used.
+    pub fn write_themed<V: Values + ?Sized>(&self, out: &mut impl Write, values: &V, theme: Option<&Theme>)
+    -> Result<(), RenderError> {
+        let default = Theme::default();
+        self.write_styled(out, values, theme.unwrap_or(&default), theme.map(|_| |style| style))
+    }
+
+    /// Without `adjust` colors are off and fallbacks are written instead.
+    fn write_styled<V: Values + ?Sized>(
+        &self, out: &mut impl Write, values: &V, theme: &Theme, adjust: Option<impl Fn(Style) -> Style>,
+    ) -> Result<(), RenderError> {
+        let mut applied = Style::default();
+        let mut link = None;
+        let mut applied_link = None;
+        let mut codes = String::new();
+        for (cmds, piece) in &self.segments {
+            if let Piece::Link(url) = piece {
+                link = url.as_deref();
+                continue;
+            }
+            if let Some(adjust) = &adjust {
+                // fallbacks stand in for the colors
+                if matches!(piece, Piece::Fallback(_) | Piece::Marker(..) | Piece::Mask) {
+                    continue;
+                }
+                let style = adjust(theme.resolve(cmds));
+                codes.clear();
+                style.transition(&applied, &mut codes);
+                if link != applied_link {
+                    add_hyperlink(&mut codes, link);
+                    applied_link = link;
+                }
+                out.write_str(&codes)?;
+                applied = style;
+            } else if cmds.contains(&Cmd::Secret) {
+                continue;
+            }
+            match piece {
+                Piece::Text(text) => out.write_str(text)?,
+                Piece::Value { name, offset } => if !values.write_value(name, out)? {
+                    return Err(RenderError::MissingValue { name: name.clone(), offset: *offset });
+                }
+                Piece::Fallback(text) => out.write_str(text)?,
+                &Piece::Marker(semantic, after) => {
+                    let fallback = theme.fallback(semantic);
+                    out.write_str(if after { fallback.after } else { fallback.before })?;
+                }
+                Piece::Mask => out.write_str(SECRET_MASK)?,
+                Piece::Link(_) => unreachable!(),
+            }
+        }
+        if !applied.is_default() {
+            codes.clear();
+            Style::default().transition(&applied, &mut codes);
+            out.write_str(&codes)?;
+        }
+        if applied_link.is_some() {
+            codes.clear();
+            add_hyperlink(&mut codes, None);
+            out.write_str(&codes)?;
+        }
+        Ok(())
+    }
+}
+impl std::str::FromStr for Template {
+    type Err = TemplateError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { Self::parse(s) }
+}
+
+fn unescape_braces(text: &str, offset: usize) -> Result<String, TemplateError> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        out.push(c);
+        match c {
+            // the parser only passes '{' on as "{{"
+            '{' => { chars.next(); }
+            '}' if chars.next().map(|(_, c)| c) != Some('}') => return Err(TemplateError {
+                kind: TemplateErrorKind::UnmatchedBrace,
+                span: offset + i..offset + i + 1,
+            }),
+            _ => (),
+        }
+    }
+    Ok(out)
+}
+
+/// A source of values for the placeholders of a [`Template`].
+///
+/// Implemented for maps from strings to [`Display`] values and for closures `Fn(&str) -> Option<impl Display>`.
+pub trait Values {
+    /// Writes the value called `name` to `out` and returns whether there is such a value.
+    fn write_value(&self, name: &str, out: &mut dyn Write) -> Result<bool, fmt::Error>;
+}
+impl<K: Borrow<str> + Hash + Eq, V: Display, S: BuildHasher> Values for HashMap<K, V, S> {
+    fn write_value(&self, name: &str, out: &mut dyn Write) -> Result<bool, fmt::Error> {
+        self.get(name).map(|value| write!(out, "{}", value)).transpose().map(|v| v.is_some())
+    }
+}
+impl<K: Borrow<str> + Ord, V: Display> Values for BTreeMap<K, V> {
+    fn write_value(&self, name: &str, out: &mut dyn Write) -> Result<bool, fmt::Error> {
+        self.get(name).map(|value| write!(out, "{}", value)).transpose().map(|v| v.is_some())
+    }
+}
+impl<F: Fn(&str) -> Option<V>, V: Display> Values for F {
+    fn write_value(&self, name: &str, out: &mut dyn Write) -> Result<bool, fmt::Error> {
+        self(name).map(|value| write!(out, "{}", value)).transpose().map(|v| v.is_some())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// No value was given for the placeholder `name` at byte `offset` of the template.
+    MissingValue { name: String, offset: usize },
+    Fmt(fmt::Error),
+}
+impl From<fmt::Error> for RenderError {
+    fn from(e: fmt::Error) -> Self { Self::Fmt(e) }
+}
+impl Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingValue { name, offset } => write!(f, "no value for placeholder '{}' at byte {}", name, offset),
+            Self::Fmt(e) => e.fmt(f),
+        }
+    }
+}
+impl std::error::Error for RenderError {}
@@ -0,0 +1,258 @@
+//! Helpers for tests of colored output.
+
+use std::{fmt, io};
+
+use color_format_core::Style;
+
+use crate::{ansi::{osc_link, sgr_params, Token, Tokens}, Span, StyledText};
+
+/// Converts text with escape codes into the tag syntax, for readable assertions.
+///
+/// Equal output always gives the same tags, no matter how the escape codes were grouped.
+/// ```
+/// use color_format::testing::to_tags;
+/// assert_eq!(to_tags("\x1b[31mred\x1b[32m\x1b[4mgreen\x1b[31m\x1b[24m red\x1b[0m"), "#r<red#u;g<green> red>");
+/// assert_eq!(to_tags("\x1b[1;31mA\x1b[22mb"), "#r<#s<A>b>");
+/// ```
+pub fn to_tags(ansi: &str) -> String {
+    StyledText::from_ansi(ansi).to_tags()
+}
+
+/// Asserts that text with escape codes shows the same as a format string of `cformat!`.
+///
+/// The text is compared by its spans and styles, so it does not matter how the escape codes are grouped.
+/// Like `cformat!`, the expected side is plain when colors are off.
+/// On failure both sides are shown as tags, see [`to_tags`], and as plain text.
+/// ```
+/// use color_format::{assert_styled_eq, config};
+/// config::set_override(true);
+/// assert_styled_eq!("\x1b[1;31merror\x1b[0m: 1", "#r;s<error>: {}", 1);
+/// assert_styled_eq!("\x1b[31m\x1b[1merror\x1b[22m\x1b[39m: 1", "#r;s<error>: {}", 1);
+/// ```
+#[macro_export]
+macro_rules! assert_styled_eq {
+    ($actual: expr, $($fmt: tt)+) => {
+        $crate::testing::assert_styled_eq(&$actual, &$crate::cformat_styled!($($fmt)+))
+    };
+}
+pub use crate::assert_styled_eq;
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_styled_eq(actual: &str, expected: &StyledText) {
+    let actual = StyledText::from_ansi(actual);
+    let expected = StyledText::from_ansi(&expected.to_string());
+    if actual != expected {
+        panic!(
+            "styled text differs\n  actual: {}\nexpected: {}\n  actual plain: {:?}\nexpected plain: {:?}",
+            actual.to_tags(), expected.to_tags(), actual.to_plain(), expected.to_plain(),
+        );
+    }
+}
+
+/// A character on a [`Screen`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+    pub link: Option<String>,
+}
+impl Default for Cell {
+    fn default() -> Self {
+        Self { ch: ' ', style: Style::default(), link: None }
+    }
+}
+
+/// A virtual terminal that interprets output into a grid of [`Cell`]s, for output that redraws lines.
+///
+/// Understands SGR styles, OSC 8 links, `\r`, `\n`, backspace, tabs, erasing in the line (`ESC[K`)
+/// and moving the cursor (`ESC[A` to `ESC[D` and `ESC[G`). Lines wrap at the width and there is no
+/// limit on the number of rows. Other escape sequences are ignored.
+/// ```
+/// use color_format::{testing::Screen, Style};
+/// let mut screen = Screen::new(20);
+/// screen.write_str("50%\r\x1b[2K\x1b[1;31mdone\x1b[0m\n");
+/// assert_eq!(screen.to_tags(), "#s;r<done>");
+/// assert_eq!(screen.cell(0, 2).unwrap().style, "bold;red".parse::<Style>().unwrap());
+/// assert_eq!(screen.cursor(), (1, 0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    width: usize,
+    rows: Vec<Vec<Cell>>,
+    row: usize,
+    col: usize,
+    style: Style,
+    link: Option<String>,
+    /// The start of an escape sequence or of a UTF-8 character that was cut off by the end of a write.
+    pending: Vec<u8>,
+}
+impl Screen {
+    /// A screen that is `width` columns wide.
+    ///
+    /// # Panics
+    ///
+    /// If `width` is 0.
+    pub fn new(width: usize) -> Self {
+        assert!(width > 0, "a screen needs at least one column");
+        Self {
+            width, rows: vec![Vec::new()], row: 0, col: 0, style: Style::default(), link: None, pending: Vec::new(),
+        }
+    }
+
+    pub fn write_str(&mut self, s: &str) {
+        self.write_bytes(s.as_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let pending = std::mem::take(&mut self.pending);
+        let (text, rest) = match std::str::from_utf8(&pending) {
+            Ok(text) => (text.to_owned(), &[][..]),
+            Err(e) if e.error_len().is_none() => {
+                let (valid, rest) = pending.split_at(e.valid_up_to());
+                (String::from_utf8(valid.to_vec()).expect("checked above"), rest)
+            }
+            Err(_) => (String::from_utf8_lossy(&pending).into_owned(), &[][..]),
+        };
+        let rest = rest.to_vec();
+        for token in Tokens(&text) {
+            match token {
+                Token::Text(text) => text.chars().for_each(|c| self.put(c)),
+                Token::Csi { params, action } => self.control(params, action),
+                Token::Osc(osc) => if let Some(url) = osc_link(osc) {
+                    self.link = url.map(str::to_owned);
+                }
+                Token::Incomplete(escape) => self.pending.extend_from_slice(escape.as_bytes()),
+            }
+        }
+        self.pending.extend(rest);
+    }
+
+    fn put(&mut self, c: char) {
+        match c {
+            '\r' => self.col = 0,
+            '\n' => self.move_to(self.row + 1, 0),
+            '\x08' => self.col = self.col.min(self.width - 1).saturating_sub(1),
+            '\t' => self.col = ((self.col / 8 + 1) * 8).min(self.width - 1),
+            c if c.is_control() => (),
+            c => {
+                // the cursor stays after the last column until the next character
+                if self.col == self.width {
+                    self.move_to(self.row + 1, 0);
+                }
+                let line = &mut self.rows[self.row];
+                if line.len() <= self.col {
+                    line.resize_with(self.col + 1, Cell::default);
+                }
+                line[self.col] = Cell { ch: c, style: self.style, link: self.link.clone() };
+                self.col += 1;
+            }
+        }
+    }
+
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.row = row;
+        self.col = col.min(self.width - 1);
+        if self.rows.len() <= row {
+            self.rows.resize_with(row + 1, Vec::new);
+        }
+    }
+
+    fn control(&mut self, params: &str, action: char) {
+        let Some(params) = sgr_params(params) else { return };
+        if action == 'm' {
+            self.style.apply_sgr_codes_lossy(&params);
+            return;
+        }
+        let n = usize::from(params[0]).max(1);
+        let col = self.col.min(self.width - 1);
+        match action {
+            'A' => self.move_to(self.row.saturating_sub(n), col),
+            'B' => self.move_to(self.row + n, col),
+            'C' => self.move_to(self.row, col + n),
+            'D' => self.move_to(self.row, col.saturating_sub(n)),
+            'G' => self.move_to(self.row, n - 1),
+            'K' => {
+                let line = &mut self.rows[self.row];
+                match params[0] {
+                    0 => line.truncate(col),
+                    1 => line.iter_mut().take(col + 1).for_each(|cell| *cell = Cell::default()),
+                    2 => line.clear(),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of rows that were written to or that the cursor moved to.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The row and column of the cursor, starting at 0.
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col.min(self.width - 1))
+    }
+
+    /// The cell at `row` and `col`, `None` if it is outside of the screen.
+    ///
+    /// Cells that were never written to or were erased are spaces in the default style.
+    pub fn cell(&self, row: usize, col: usize) -> Option<Cell> {
+        let line = self.rows.get(row)?;
+        (col < self.width).then(|| line.get(col).cloned().unwrap_or_default())
+    }
+
+    /// The cells of a row up to the last one that is not an unstyled space.
+    pub fn row(&self, row: usize) -> &[Cell] {
+        let line = self.rows.get(row).map_or(&[][..], Vec::as_slice);
+        let end = line.iter().rposition(|cell| *cell != Cell::default()).map_or(0, |i| i + 1);
+        &line[..end]
+    }
+
+    /// The styled text of a row, see [`Screen::row`].
+    pub fn row_text(&self, row: usize) -> StyledText {
+        self.row(row).iter().map(|cell| Span {
+            link: cell.link.clone(),
+            ..Span::new(cell.ch, cell.style)
+        }).collect()
+    }
+
+    /// The rows without styles, without trailing empty rows.
+    pub fn to_plain(&self) -> String {
+        self.lines(|text| text.to_plain())
+    }
+
+    /// A snapshot of the rows in the tag syntax, see [`to_tags`], without trailing empty rows.
+    pub fn to_tags(&self) -> String {
+        self.lines(|text| text.to_tags())
+    }
+
+    fn lines(&self, line: impl Fn(StyledText) -> String) -> String {
+        let mut lines = (0..self.rows.len()).map(|row| line(self.row_text(row))).collect::<Vec<_>>();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+}
+impl fmt::Write for Screen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        Screen::write_str(self, s);
+        Ok(())
+    }
+}
+impl io::Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_bytes(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use std::{cell::Cell, fmt, fs::File, io::{self, IsTerminal}};
+
+use crate::config::config;
+
+/// A writer that knows whether output written to it should be colorized.
+///
+/// [`cwrite!`](crate::cwrite) and [`cwriteln!`](crate::cwriteln) consult this before
+/// falling back to the global [`config`](crate::config::config).
+pub trait ColorChoice {
+    fn colorize(&self) -> bool;
+}
+
+macro_rules! terminal_choice {
+    ($($t: ty),*) => {
+        $(
+            impl ColorChoice for $t {
+                fn colorize(&self) -> bool {
+                    config().colorize_for(self.is_terminal())
+                }
+            }
+        )*
+    };
+}
+terminal_choice!(io::Stdout, io::StdoutLock<'_>, io::Stderr, io::StderrLock<'_>, File);
+
+impl ColorChoice for Vec<u8> {
+    fn colorize(&self) -> bool {
+        config().colorize_for(false)
+    }
+}
+impl<T: ColorChoice + ?Sized> ColorChoice for &T {
+    fn colorize(&self) -> bool { T::colorize(self) }
+}
+impl<T: ColorChoice + ?Sized> ColorChoice for &mut T {
+    fn colorize(&self) -> bool { T::colorize(self) }
+}
+impl<T: ColorChoice + ?Sized> ColorChoice for Box<T> {
+    fn colorize(&self) -> bool { T::colorize(self) }
+}
+
+/// Wraps a writer together with its own colorization decision.
+#[derive(Debug)]
+pub struct ColorWriter<W> {
+    inner: W,
+    colorize: bool,
+}
+impl<W> ColorWriter<W> {
+    pub fn new(inner: W, colorize: bool) -> Self {
+        Self { inner, colorize }
+    }
+    /// Decides using [`IsTerminal`] while still respecting the environment and overrides.
+    pub fn detect(inner: W) -> Self where W: IsTerminal {
+        let colorize = config().colorize_for(inner.is_terminal());
+        Self { inner, colorize }
+    }
+    pub fn set_colorize(&mut self, colorize: bool) {
+        self.colorize = colorize;
+    }
+    pub fn get_ref(&self) -> &W { &self.inner }
+    pub fn get_mut(&mut self) -> &mut W { &mut self.inner }
+    pub fn into_inner(self) -> W { self.inner }
+}
+impl<W> ColorChoice for ColorWriter<W> {
+    fn colorize(&self) -> bool { self.colorize }
+}
+impl<W: io::Write> io::Write for ColorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> { self.inner.write(buf) }
+    fn flush(&mut self) -> io::Result<()> { self.inner.flush() }
+}
+impl<W: fmt::Write> fmt::Write for ColorWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result { self.inner.write_str(s) }
+}
+
+thread_local! {
+    static PLAIN_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Whether the current thread is formatting a value wrapped in [`plain`].
+pub(crate) fn plain_active() -> bool {
+    PLAIN_DEPTH.with(Cell::get) != 0
+}
+
+/// Formats `value` without colors, even if colors are enabled globally.
+///
+/// This allows one `Display` impl that uses [`cwrite!`](crate::cwrite) to serve both
+/// colored terminal output and plain text, for example for log lines:
+/// ```
+/// use color_format::*;
+/// struct Status;
+/// impl std::fmt::Display for Status {
+///     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+///         cwrite!(f, "#g<ok>")
+///     }
+/// }
+/// config::set_override(true);
+/// assert_eq!(Status.to_string(), "\x1b[32mok\x1b[0m");
+/// assert_eq!(plain(&Status).to_string(), "ok");
+/// ```
+/// Writers with their own decision, like a [`ColorWriter`], are not affected.
+pub fn plain<T: ?Sized>(value: &T) -> Plain<'_, T> {
+    Plain(value)
+}
+
+/// Returned by [`plain`].
+pub struct Plain<'a, T: ?Sized>(&'a T);
+
+struct PlainGuard;
+impl PlainGuard {
+    fn enter() -> Self {
+        PLAIN_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        PlainGuard
+    }
+}
+impl Drop for PlainGuard {
+    fn drop(&mut self) {
+        PLAIN_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+impl<T: fmt::Display + ?Sized> fmt::Display for Plain<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let _guard = PlainGuard::enter();
+        self.0.fmt(f)
+    }
+}
+impl<T: fmt::Debug + ?Sized> fmt::Debug for Plain<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let _guard = PlainGuard::enter();
+        self.0.fmt(f)
+    }
+}
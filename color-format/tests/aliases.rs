@@ -0,0 +1,24 @@
+use color_format::*;
+
+mod support;
+
+#[test]
+fn manifest_aliases() {
+    let _lock = support::lock();
+    config::set_override(true);
+    // defined in [package.metadata.color-format] of this crate's Cargo.toml
+    assert_eq!(cformat!("#failure<error>: #file<{}>", "a.rs"), cformat!("#r!;s<error>: #u;c<{}>", "a.rs"));
+    assert_eq!(cformat!("#failure;i<x>"), cformat!("#r!;s;i<x>"));
+}
+
+#[test]
+fn runtime_aliases() {
+    let mut aliases = Aliases::new();
+    aliases.insert("error", "bright-red;bold").unwrap();
+    assert_eq!(aliases.insert("bold", "red").unwrap_err().kind, TemplateErrorKind::InvalidAlias("bold".to_owned()));
+    assert_eq!(aliases.insert("a b", "red").unwrap_err().kind, TemplateErrorKind::InvalidAlias("a b".to_owned()));
+    assert_eq!(aliases.insert("x", "nope").unwrap_err().kind, TemplateErrorKind::UnknownTag("nope".to_owned()));
+    let template = Template::parse_with_aliases("#error<{x}>", &aliases).unwrap();
+    assert_eq!(template.render_with(&|_: &str| Some(1), true).unwrap(), "\x1b[91m\x1b[1m1\x1b[0m");
+    assert_eq!(Template::parse("#failure<x>").unwrap_err().kind, TemplateErrorKind::UnknownTag("failure".to_owned()));
+}
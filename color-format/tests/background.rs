@@ -0,0 +1,40 @@
+use color_format::{config::{self, Background}, *};
+
+mod support;
+
+fn detect(colorfgbg: Option<&str>, background: Option<&str>) -> Background {
+    match colorfgbg {
+        Some(value) => std::env::set_var("COLORFGBG", value),
+        None => std::env::remove_var("COLORFGBG"),
+    }
+    match background {
+        Some(value) => std::env::set_var(config::BACKGROUND_VAR, value),
+        None => std::env::remove_var(config::BACKGROUND_VAR),
+    }
+    config::refresh();
+    config::background()
+}
+
+#[test]
+fn background_from_env() {
+    let _lock = support::lock();
+    config::set_override(true);
+    assert_eq!(detect(None, None), Background::Unknown);
+    assert_eq!(config::theme(), Theme::dark());
+    assert_eq!(detect(Some("15;0"), None), Background::Dark);
+    assert_eq!(detect(Some("0;15"), None), Background::Light);
+    assert_eq!(detect(Some("0;default;7"), None), Background::Light);
+    assert_eq!(detect(Some("default;default"), None), Background::Unknown);
+    assert_eq!(detect(Some("0;15"), Some("dark")), Background::Dark);
+    assert_eq!(detect(Some("nonsense"), Some("Light")), Background::Light);
+    assert_eq!(detect(Some("15;0"), Some("sepia")), Background::Dark);
+
+    detect(Some("0;15"), None);
+    assert_eq!(config::theme(), Theme::light());
+    assert_eq!(cformat!("#warn<careful>"), cformat!("#s;rgb(175,95,0)<careful>"));
+    assert!(config::explain().to_string().contains("background: light"));
+    config::set_theme(Theme::high_contrast());
+    assert_eq!(config::theme(), Theme::high_contrast());
+    config::unset_theme();
+    assert_eq!(config::theme(), Theme::light());
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use color_format::*;
+
+mod support;
+
+#[test]
+fn colorblind_safe_colors() {
+    let _lock = support::lock();
+    config::set_override(true);
+    std::env::set_var("COLORTERM", "truecolor");
+    std::env::remove_var(config::COLORBLIND_VAR);
+    config::refresh();
+    assert!(!config::colorblind());
+    assert!(config::truecolor());
+    assert_eq!(cformat!("#r<-{}> #g<+{}>", 1, 2), "\x1b[31m-1\x1b[0m \x1b[32m+2\x1b[0m");
+
+    config::set_colorblind(true);
+    assert_eq!(cformat!("#r<-{}> #g<+{}>", 1, 2), cformat!("#rgb(230,159,0)<-{}> #rgb(0,114,178)<+{}>", 1, 2));
+    assert_eq!(cformat!("#s;_g!<ok>"), cformat!("#s;_rgb(86,180,233)<ok>"));
+    assert_eq!(cformat!("#c<unchanged>"), "\x1b[36munchanged\x1b[0m");
+    let template = Template::parse("#r<{x}>").unwrap();
+    let values = HashMap::from([("x", "failed")]);
+    assert_eq!(template.render(&values).unwrap(), cformat!("#rgb(230,159,0)<failed>"));
+
+    std::env::remove_var("COLORTERM");
+    config::refresh();
+    assert_eq!(cformat!("#r<-{}> #g<+{}>", 1, 2), cformat!("#y<-{}> #b<+{}>", 1, 2));
+    assert_eq!(template.render(&values).unwrap(), cformat!("#y<failed>"));
+
+    config::set_colorblind(false);
+    std::env::set_var(config::COLORBLIND_VAR, "1");
+    config::refresh();
+    assert!(!config::colorblind());
+    config::unset_colorblind();
+    assert!(config::colorblind());
+    assert!(config::explain().to_string().contains("colorblind-safe colors: true"));
+    assert_eq!(cformat!("#g<+>"), cformat!("#b<+>"));
+}
@@ -0,0 +1,61 @@
+use std::{ffi::OsStr, process::Command};
+
+use color_format::config;
+
+mod support;
+
+#[test]
+fn explain_override() {
+    let _lock = support::lock();
+    config::set_override(true);
+    let report = config::explain();
+    assert!(report.colorize);
+    assert_eq!(report.override_colorize, Some(true));
+    assert_eq!(report.reason, config::Reason::Override);
+    assert!(report.to_string().starts_with("color-format: colors on because an override was set"));
+    assert_eq!(
+        report.env.iter().map(|(name, _)| *name).collect::<Vec<_>>(),
+        ["CLICOLOR_FORCE", "CLICOLOR", "NO_COLOR"]
+    );
+
+    config::unset_override();
+    let report = config::explain();
+    assert_eq!(report.override_colorize, None);
+    assert_eq!(report.colorize, report.detected);
+    assert_ne!(report.reason, config::Reason::Override);
+}
+
+fn env<'a>(command: &'a Command, name: &str) -> Option<Option<&'a OsStr>> {
+    command.get_envs().find(|(var, _)| *var == name).map(|(_, value)| value)
+}
+
+#[test]
+fn apply_to_command() {
+    let _lock = support::lock();
+    let tools = [config::Tool::Cargo, config::Tool::Custom { var: "MY_COLOR", on: "yes", off: "no" }];
+
+    config::set_override(true);
+    let mut command = Command::new("true");
+    config::apply_to_command_for(&mut command, &tools);
+    assert_eq!(env(&command, "CLICOLOR_FORCE"), Some(Some("1".as_ref())));
+    assert_eq!(env(&command, "FORCE_COLOR"), Some(Some("1".as_ref())));
+    assert_eq!(env(&command, "NO_COLOR"), Some(None));
+    assert_eq!(env(&command, "CARGO_TERM_COLOR"), Some(Some("always".as_ref())));
+    assert_eq!(env(&command, "MY_COLOR"), Some(Some("yes".as_ref())));
+
+    config::set_override(false);
+    let mut command = Command::new("true");
+    config::apply_to_command(&mut command);
+    assert_eq!(env(&command, "CLICOLOR_FORCE"), Some(None));
+    assert_eq!(env(&command, "FORCE_COLOR"), Some(None));
+    assert_eq!(env(&command, "NO_COLOR"), Some(Some("1".as_ref())));
+    assert_eq!(env(&command, "CARGO_TERM_COLOR"), None);
+
+    // appended to the parameters passed on from the parent
+    let mut command = Command::new("true");
+    command.env("GIT_CONFIG_PARAMETERS", "'core.pager=less'");
+    config::apply_to_command_for(&mut command, &[config::Tool::Git]);
+    assert_eq!(env(&command, "GIT_CONFIG_PARAMETERS"), Some(Some("'core.pager=less' 'color.ui=never'".as_ref())));
+
+    config::unset_override();
+}
@@ -0,0 +1,51 @@
+use color_format::{env_colors::*, BaseColor, BasicColor, Boldness, Color, Intensity, RgbColor, SgrError, Style};
+
+#[test]
+fn ls_colors() {
+    let ls = EnvStyles::parse("rs=0:di=01;34:ln=01;36:ex=38;5;208:or=40;31;01:*.tar.gz=01;31:*.gz=31:");
+    assert_eq!(ls.get("di"), Some(Style {
+        boldness: Boldness::Bold,
+        fg_color: Color::Basic(BasicColor::new(BaseColor::Blue, Intensity::Normal)),
+        ..Style::default()
+    }));
+    assert_eq!(ls.get("rs"), Some(Style::default()));
+    assert_eq!(ls.get("ex").unwrap().fg_color, Color::Rgb(RgbColor { r: 255, g: 135, b: 0 }));
+    assert_eq!(ls.get("or").unwrap().to_string(), "bold;red;bg:black");
+    assert_eq!(ls.for_file_name("a.tar.gz"), Some("bold;red".parse().unwrap()));
+    assert_eq!(ls.for_file_name("a.gz"), Some("red".parse().unwrap()));
+    assert_eq!(ls.for_file_name("a.rs"), None);
+    assert_eq!(ls.get("nope"), None);
+    assert!(ls.errors().is_empty());
+
+    // unsupported entries are skipped
+    let ls = EnvStyles::parse("rs=0:di=01;34:ln=target:mi=00:*.rs=01;53:*.gz=31");
+    assert_eq!(ls.get("di").unwrap().to_string(), "bold;blue");
+    assert_eq!(ls.get("ln"), None);
+    assert_eq!(ls.for_file_name("a.gz"), Some("red".parse().unwrap()));
+    assert_eq!(ls.for_file_name("a.rs"), None);
+    assert_eq!(ls.errors().iter().map(|e| e.key.as_str()).collect::<Vec<_>>(), ["ln", "*.rs"]);
+}
+
+#[test]
+fn grep_and_gcc_colors() {
+    let grep = EnvStyles::parse("ms=01;31:mc=01;31:sl=:cx=:fn=35:ln=32:bn=32:se=36:ne");
+    assert_eq!(grep.get("fn").unwrap().to_string(), "magenta");
+    assert_eq!(grep.get("sl"), Some(Style::default()));
+    assert!(grep.has_flag("ne"));
+    assert_eq!(grep.flags().collect::<Vec<_>>(), ["ne"]);
+
+    let gcc = EnvStyles::parse("error=01;31:warning=01;35:note=01;36:caret=01;32:locus=01:quote=01");
+    assert_eq!(gcc.get("warning").unwrap().to_string(), "bold;magenta");
+    assert_eq!(gcc.iter().count(), 6);
+}
+
+#[test]
+fn errors() {
+    let errors = EnvStyles::parse("di=01;34:ln=01;53").errors().to_vec();
+    assert_eq!(errors, [EnvStyleError { key: "ln".to_owned(), error: SgrError::Unsupported(53) }]);
+    assert_eq!(errors[0].to_string(), "invalid style for 'ln': SGR code 53 is not supported");
+    let error = |s| EnvStyles::parse(s).errors()[0].error.clone();
+    assert_eq!(error("di=x"), SgrError::InvalidParam("x".to_owned()));
+    assert_eq!(error("di=38;5"), SgrError::InvalidColorArgs);
+    assert_eq!(error("di=38;3;1"), SgrError::InvalidColorMode(3));
+}
@@ -1,9 +1,11 @@
 use color_format::*;
 
-
+mod support;
 
 #[test]
 fn escapes() {
+    let _lock = support::lock();
+    config::set_override(true);
     let x = 3;
     // '#' doesn't have to be escaped inside format strings
     // '<' doesn't ever have to be escaped but can be for symmetry
@@ -19,6 +21,8 @@ fn escapes() {
 
 #[test]
 fn recursive_tags() {
+    let _lock = support::lock();
+    config::set_override(true);
     assert_eq!(
         cformat!("uncolored, #r<red#g;u<green and underlined>,red again>, uncolored"),
         "uncolored, \u{1b}[31mred\u{1b}[32m\u{1b}[4mgreen and underlined\u{1b}[31m\u{1b}[24m,red again\u{1b}[0m, uncolored"
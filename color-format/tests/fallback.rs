@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use color_format::*;
+
+mod support;
+
+#[test]
+fn macro_fallbacks() {
+    let _lock = support::lock();
+    config::set_override(false);
+    config::unset_theme();
+    assert_eq!(cformat!(r#"#r(fallback="[!] ")<FAILED> #g<ok>"#), "[!] FAILED ok");
+    assert_eq!(cformat!("#s(fallback='*', fallback-end = '*')<{}>", "bold"), "*bold*");
+    assert_eq!(cformat!("#r(fallback='{{x}}')<{}>", 1), "{{x}}1");
+    assert_eq!(cformat!("#error<failed>: #path<{}>", "a.rs"), "[!] failed: 'a.rs'");
+    assert_eq!(cformat!("#error<#code<x>> #warn;path<y>"), "[!] `x` [warning] 'y'");
+    assert_eq!(cformat!("#error(fallback='E: ')<x>"), "E: x");
+    assert_eq!(cformat!("#rgb(1,2,3)(fallback='>')<x>"), ">x");
+
+    let mut theme = Theme::default();
+    theme.set_fallback(Semantic::Path, Fallback::new("<", ">"));
+    config::set_theme(theme);
+    assert_eq!(cformat!("#path<{}>", "a.rs"), "<a.rs>");
+    config::unset_theme();
+
+    config::set_override(true);
+    assert_eq!(cformat!(r#"#r(fallback="[!] ")<FAILED>"#), cformat!("#r<FAILED>"));
+    assert_eq!(cformat!("#error<failed>"), cformat!("#s;r!<failed>"));
+}
+
+#[test]
+fn template_fallbacks() {
+    let _lock = support::lock();
+    // the global theme decides the fallbacks of semantic tags
+    config::set_theme(Theme::dark());
+    let values = HashMap::from([("file", "a.rs")]);
+    let template = Template::parse("#r(fallback='[!] ', fallback-end='!')<{file}> #path<{file}>").unwrap();
+    assert_eq!(template.render_with(&values, false).unwrap(), "[!] a.rs! 'a.rs'");
+    assert_eq!(
+        template.render_with(&values, true).unwrap(),
+        "\x1b[31ma.rs\x1b[0m \x1b[96m\x1b[4ma.rs\x1b[0m",
+    );
+    let mut out = String::new();
+    template.write_themed(&mut out, &values, None).unwrap();
+    assert_eq!(out, "[!] a.rs! 'a.rs'");
+    config::unset_theme();
+}
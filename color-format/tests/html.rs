@@ -0,0 +1,65 @@
+use color_format::{html::*, *};
+
+#[test]
+fn macro_html() {
+    let name = "<a & 'b'>";
+    assert_eq!(cformat_html!("plain {}", name), "plain &lt;a &amp; &#39;b&#39;&gt;");
+    assert_eq!(cformat_html!("#u<#> {{x}}>"), "<span style=\"text-decoration:underline\">&gt; {x}</span>");
+    assert_eq!(
+        cformat_html!("#r<a#s<b>> #_rgb(0,0,80);i<c>"),
+        "<span style=\"color:#cd0000\">a</span><span style=\"font-weight:bold;color:#cd0000\">b</span> \
+         <span style=\"font-style:italic;background-color:#000050\">c</span>"
+    );
+    assert_eq!(
+        cformat_html!("#(link='https://a.example/?a=1&b')<x #g<y>>"),
+        "<a href=\"https://a.example/?a=1&amp;b\">x <span style=\"color:#00cd00\">y</span></a>"
+    );
+    // fallbacks are left out and secrets masked
+    assert_eq!(cformat_html!("#error<x> #secret(fallback='pw=')<{}>", "hunter2"), cformat_html!("#s;r!<x> ********"));
+    assert_eq!(cformat_html!("{:!raw}", "<br>"), "<br>");
+    assert_eq!(cformat_html!(#[sanitize] "{}", "\x1b"), "\\u{1b}");
+}
+
+#[test]
+fn same_as_macro() {
+    macro_rules! check {
+        ($($fmt: tt)*) => {
+            assert_eq!(to_html(&cformat_styled!($($fmt)*)), cformat_html!($($fmt)*));
+        };
+    }
+    check!("#r<a#s<b>> #_rgb(0,0,80);i<c> {}", "<&>");
+    check!("#error<failed>: #path<{}> #secret<{}>", "a.rs", 1);
+    check!("#u(link='https://a.example')<a #i(link='https://b.example')<b>> c");
+    check!("#f;blink;reverse;conceal;strike<{:>4}>", 1);
+}
+
+#[test]
+fn runtime_html() {
+    let ansi = "\x1b[1;31mbold red\x1b[0m \x1b[7mreversed\x1b[27m \x1b[38;5;208m<x>";
+    assert_eq!(
+        to_html(ansi),
+        "<span style=\"font-weight:bold;color:#cd0000\">bold red</span> \
+         <span style=\"color:#000000;background-color:#e5e5e5\">reversed</span> \
+         <span style=\"color:#ff8700\">&lt;x&gt;</span>"
+    );
+    let mut options = HtmlOptions { class_prefix: Some("cf-".to_owned()), ..HtmlOptions::default() };
+    options.palette.colors[1] = RgbColor { r: 255, g: 0, b: 0 };
+    assert_eq!(
+        to_html_with(ansi, &options),
+        "<span class=\"cf-bold cf-fg-red\">bold red</span> <span class=\"cf-reverse\">reversed</span> \
+         <span style=\"color:#ff8700\">&lt;x&gt;</span>"
+    );
+    let css = options.stylesheet();
+    assert!(css.contains(".cf-bold { font-weight: bold; }\n"));
+    assert!(css.contains(".cf-fg-red { color: #ff0000; }\n"));
+    assert!(css.contains(".cf-bg-bright-white { background-color: #ffffff; }\n"));
+    assert_eq!(HtmlOptions::default().stylesheet(), "");
+}
+
+#[test]
+fn unsafe_links() {
+    let ansi = "\x1b]8;;javascript:alert(1)\x1b\\a\x1b]8;;\x1b\\ \x1b]8;;MAILTO:x@a.example\x1b\\b\x1b]8;;\x1b\\";
+    assert_eq!(to_html(ansi), "a <a href=\"MAILTO:x@a.example\">b</a>");
+    assert_eq!(cformat_html!("#(link=' javascript:x')<a> #(link='data:text/html,x')<b>"), "a b");
+    assert!(html::is_safe_link("file:///tmp/a.html") && !html::is_safe_link("a.html"));
+}
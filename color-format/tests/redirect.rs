@@ -0,0 +1,71 @@
+//! Redirects stdout and stderr in a child process and checks that `config::refresh`
+//! and `config::set_recheck` pick up the change.
+#![cfg(unix)]
+
+use std::{ffi::CStr, process::Command};
+
+use color_format::config;
+
+const CHILD_VAR: &str = "COLOR_FORMAT_REDIRECT_CHILD";
+
+#[test]
+fn redirect() {
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "redirect_child", "--test-threads=1", "--nocapture"])
+        .env(CHILD_VAR, "1")
+        .env_remove("CLICOLOR_FORCE")
+        .env_remove("CLICOLOR")
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "child failed: {}", String::from_utf8_lossy(&output.stdout));
+}
+
+fn open_pty() -> libc::c_int {
+    unsafe {
+        let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+        assert!(master >= 0, "posix_openpt failed");
+        assert_eq!(libc::grantpt(master), 0);
+        assert_eq!(libc::unlockpt(master), 0);
+        let name = CStr::from_ptr(libc::ptsname(master)).to_owned();
+        let slave = libc::open(name.as_ptr(), libc::O_RDWR | libc::O_NOCTTY);
+        assert!(slave >= 0, "opening {:?} failed", name);
+        slave
+    }
+}
+
+fn redirect_to(fd: libc::c_int) {
+    unsafe {
+        assert_eq!(libc::dup2(fd, 1), 1);
+        assert_eq!(libc::dup2(fd, 2), 2);
+    }
+}
+
+#[test]
+fn redirect_child() {
+    if std::env::var_os(CHILD_VAR).is_none() {
+        return;
+    }
+    let (stdout, stderr) = unsafe { (libc::dup(1), libc::dup(2)) };
+    let pty = open_pty();
+    let null = unsafe { libc::open(c"/dev/null".as_ptr(), libc::O_WRONLY) };
+
+    let mut seen = Vec::new();
+    redirect_to(null);
+    seen.push(config::config().colorize());
+    redirect_to(pty);
+    seen.push(config::config().colorize());
+    config::refresh();
+    seen.push(config::config().colorize());
+    config::set_recheck(true);
+    redirect_to(null);
+    seen.push(config::config().colorize());
+    let report = config::explain();
+
+    unsafe {
+        libc::dup2(stdout, 1);
+        libc::dup2(stderr, 2);
+    }
+    assert_eq!(seen, [false, false, true, false]);
+    assert!(report.recheck && !report.stdout_terminal && !report.colorize);
+}
@@ -0,0 +1,39 @@
+use color_format::{roff::*, *};
+
+#[test]
+fn macro_roff() {
+    assert_eq!(cformat_roff!("plain {}", r"a\b-c"), r"plain a\eb\-c");
+    assert_eq!(cformat_roff!("#s<a#i<b>>#u<c> #r<d>"), r"\fBa\f(BIb\fIc\fR d");
+    // requests at the start of a line are escaped, in the template and in values
+    assert_eq!(cformat_roff!(".a\n'b {}", "\n.c"), "\\&.a\n\\&'b \n\\&.c");
+    assert_eq!(cformat_roff!("{}\n{}.d", ".e", "\n"), "\\&.e\n\n\\&.d");
+    // fallbacks and links are left out and secrets masked
+    assert_eq!(
+        cformat_roff!("#error(link='https://x.example')<x> #secret(fallback='pw=')<{}>", "hunter2"),
+        r"\fBx\fR ********",
+    );
+    assert_eq!(cformat_roff!("{:!raw}", r"\fB"), r"\fB");
+    assert_eq!(cformat_roff!(#[sanitize] "{}", "\x1b"), r"\eu{1b}");
+}
+
+#[test]
+fn same_as_macro() {
+    macro_rules! check {
+        ($($fmt: tt)*) => {
+            assert_eq!(to_roff(&cformat_styled!($($fmt)*)), cformat_roff!($($fmt)*));
+        };
+    }
+    check!("#s<--help>\n    Print #i<help> {}", "-h");
+    check!("#error<failed>: #path<{}> #secret<{}>", "a.rs", 1);
+    check!(".#u<a>\n'{}'", "b");
+}
+
+#[test]
+fn runtime_roff() {
+    let ansi = "\x1b[1;31mbold red\x1b[0m\n.\x1b[4;92mx\x1b[22m";
+    assert_eq!(to_roff(ansi), "\\fBbold red\\fR\n\\&.\\fIx\\fR");
+    assert_eq!(
+        to_roff_with(ansi, &RoffOptions { colors: true }),
+        "\\fB\\m[red]bold red\\fR\\m[]\n\\&.\\fI\\m[green]x\\fR\\m[]",
+    );
+}
@@ -0,0 +1,73 @@
+use std::fmt::{self, Write};
+
+use color_format::*;
+
+mod support;
+
+struct Colored;
+impl fmt::Display for Colored {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\x1b[31mred\x1b[0m")
+    }
+}
+
+#[test]
+fn sanitize() {
+    let _lock = support::lock();
+    config::set_override(true);
+    config::set_sanitize(false);
+    let name = "a\x1b]0;x\x07\rb\n";
+    assert_eq!(cformat!(#[sanitize] "#r<{}>", name), "\x1b[31ma\\u{1b}]0;x\\u{7}\\rb\n\x1b[0m");
+    assert_eq!(cformat!("#r<{}>", name), format!("\x1b[31m{}\x1b[0m", name));
+    assert_eq!(cformat!(#[sanitize] "{name:!raw} {0}", Colored), format!("{} \\u{{1b}}[31mred\\u{{1b}}[0m", name));
+    assert_eq!(cformat!(#[sanitize] "{:!raw}", Colored), Colored.to_string());
+    assert_eq!(cformat!(#[sanitize] "{:?}", "\x1b"), r#""\u{1b}""#);
+    assert_eq!(cformat!(#[sanitize] "{}", '\u{9b}'), "\\u{9b}");
+
+    // widths and precisions are not wrapped
+    let width = 6;
+    // padding is computed before escaping
+    assert_eq!(cformat!(#[sanitize] "[{:>1$}]", "\x07", 4), "[   \\u{7}]");
+    assert_eq!(cformat!(#[sanitize] "[{:>width$}|{:.*}|{x:#06x}]", "ab", 1, 1.25, x = 255), "[    ab|1.2|0x00ff]");
+    assert_eq!(cformat!(#[sanitize] "[{0:^w$.p$}]", "abcdef", w = 5, p = 3), "[ abc ]");
+    // the type is not part of the precision
+    assert_eq!(cformat!(#[sanitize] "{:.2e}", 1234.5), "1.23e3");
+    assert_eq!(cformat!(#[sanitize] "[{:8.3e}]", 1234.5), "[ 1.234e3]");
+    assert_eq!(cformat!(#[sanitize] "{:.3x?}", [1.0, 0.5]), format!("{:.3x?}", [1.0, 0.5]));
+    assert_eq!(
+        cformat!(#[sanitize] "[{:>2$.2e}|{:08.1}]", 0.5, -2.5, 10),
+        format!("[{:>2$.2e}|{:08.1}]", 0.5, -2.5, 10),
+    );
+    assert_eq!(cformat!("{:.2e} {:8.3e}", 1234.5, 1.0), "1.23e3  1.000e0");
+    assert_eq!(
+        cformat!(#[sanitize] "[{:>1$}|{:.*}|{0:#?}]", "ab", 5, 1.5),
+        format!("[{:>1$}|{:.*}|{0:#?}]", "ab", 5, 1.5),
+    );
+    assert_eq!(
+        cformat!(#[sanitize] "[{:x<5.2e}|{:x<9.2e}]", 0.5, 1234.5),
+        format!("[{:x<5.2e}|{:x<9.2e}]", 0.5, 1234.5),
+    );
+    let (w, p) = (9, 3);
+    assert_eq!(
+        cformat!(#[sanitize] "[{:>w$.p$}|{v:^width$.prec$e}|{v:<w$}]", 0.5, v = 2.25, width = 10, prec = 1),
+        format!("[{:>w$.p$}|{v:^width$.prec$e}|{v:<w$}]", 0.5, v = 2.25, width = 10, prec = 1),
+    );
+
+    let mut out = String::new();
+    cwrite!(out, #[sanitize] "#g<{}>", "\x1b").unwrap();
+    assert_eq!(out, "\x1b[32m\\u{1b}\x1b[0m");
+
+    config::set_sanitize(true);
+    assert_eq!(cformat!("#r<{}>", name), "\x1b[31ma\\u{1b}]0;x\\u{7}\\rb\n\x1b[0m");
+    assert_eq!(cformat!("{}", plain(&Colored)), "\\u{1b}[31mred\\u{1b}[0m");
+    assert_eq!(cformat!("#error<{:!raw}>", Colored), cformat!("#error<\x1b[31mred\x1b[0m>"));
+    config::set_override(false);
+    assert_eq!(cformat!("#u<{}>", "\x1b"), "\\u{1b}");
+    config::set_sanitize(false);
+}
+
+#[test]
+fn escape_control_chars() {
+    assert!(matches!(sanitize::escape_control_chars("plain\ttext\n"), std::borrow::Cow::Borrowed(_)));
+    assert_eq!(sanitize::escape_control_chars("\x1b[2J\x7f"), "\\u{1b}[2J\\u{7f}");
+}
@@ -0,0 +1,40 @@
+use std::{collections::HashMap, io::Write};
+
+use color_format::*;
+
+mod support;
+
+#[test]
+fn secret_macros() {
+    let _lock = support::lock();
+    let token = "hunter2";
+    config::set_override(false);
+    assert_eq!(cformat!("token: #secret<{}>", token), "token: ********");
+    assert_eq!(cformat!("#secret<{0}> {1} #secret<{0:>9}>", token, 1), "******** 1 ********");
+    assert_eq!(cformat!("#secret;conceal<{token}>"), "********");
+    assert_eq!(cformat!("#secret<a #r(fallback='!')<{}> b>", token), "********");
+    assert_eq!(cformat!("#secret(fallback='[', fallback-end=']')<{}>", token), "[********]");
+    assert_eq!(cformat!("#error<#secret<{}>>", token), "[!] ********");
+    // the other placeholders are passed on as they are
+    assert_eq!(cformat!("{} #secret<{}> {:.2e}", 1, token, 1234.5), "1 ******** 1.23e3");
+    assert_eq!(cformat!("#secret<{:>1$x}>|{}", 255, 4), "********|4");
+    assert_eq!(cformat!("#secret<{0}> {0:?}", token), "******** \"hunter2\"");
+
+    config::set_override(true);
+    assert_eq!(cformat!("token: #secret<{}>", token), "token: hunter2");
+    assert_eq!(cformat!("{} #secret<{}> {:.2e}", 1, token, 1234.5), "1 hunter2 1.23e3");
+    assert_eq!(cformat!("#secret;conceal<{}>", token), cformat!("#conceal<{}>", token));
+    // writers can decide on their own
+    let mut out = ColorWriter::new(Vec::new(), false);
+    cwrite!(out, "#secret<{}>", token).unwrap();
+    out.flush().unwrap();
+    assert_eq!(out.into_inner(), b"********");
+}
+
+#[test]
+fn secret_template() {
+    let template = Template::parse("#g<user> #secret(fallback='pw=')<#r<{pw}>>!").unwrap();
+    let values = HashMap::from([("pw", "hunter2")]);
+    assert_eq!(template.render_with(&values, false).unwrap(), "user pw=********!");
+    assert_eq!(template.render_with(&values, true).unwrap(), "\x1b[32muser\x1b[0m \x1b[31mhunter2\x1b[0m!");
+}
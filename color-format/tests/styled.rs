@@ -0,0 +1,94 @@
+use color_format::*;
+
+mod support;
+
+#[test]
+fn spans() {
+    let text = cformat_styled!("#r<error:> {} #g;s<{:>3}>", "x", 1);
+    let red: Style = "r".parse().unwrap();
+    let green: Style = "g;s".parse().unwrap();
+    assert_eq!(text.spans(), [Span::new("error:", red), Span::new(" x ", Style::default()), Span::new("  1", green)]);
+    assert!(cformat_styled!("").is_empty());
+    assert_eq!(cformat_styled!("{{#r<}}>").spans(), [Span::new("{", Style::default()), Span::new("}", red)]);
+}
+
+#[test]
+fn kinds_and_links() {
+    let text = cformat_styled!("#u(link='https://example.com/{x}')<a#secret(fallback='pw=')<{}>> b", "hunter2");
+    let link = Some("https://example.com/{x}".to_owned());
+    let kinds = text.spans().iter().map(|span| (span.text.as_str(), span.link.clone(), span.kind)).collect::<Vec<_>>();
+    assert_eq!(kinds, [
+        ("a", link.clone(), SpanKind::Text),
+        ("pw=", link.clone(), SpanKind::Fallback),
+        ("********", link.clone(), SpanKind::Mask),
+        ("hunter2", link, SpanKind::Secret),
+        (" b", None, SpanKind::Text),
+    ]);
+    assert_eq!(text.to_plain(), "apw=******** b");
+    assert_eq!(
+        text.to_ansi(),
+        "\x1b[4m\x1b]8;;https://example.com/{x}\x1b\\ahunter2\x1b[0m\x1b]8;;\x1b\\ b",
+    );
+}
+
+#[test]
+fn same_as_cformat() {
+    let _lock = support::lock();
+    config::unset_theme();
+    let name = "a\x07.rs";
+    macro_rules! check {
+        ($($fmt: tt)*) => {
+            let text = cformat_styled!($($fmt)*);
+            config::set_override(true);
+            assert_eq!(text.to_ansi(), cformat!($($fmt)*));
+            assert_eq!(text.to_string(), text.to_ansi());
+            config::set_override(false);
+            assert_eq!(text.to_plain(), cformat!($($fmt)*));
+            assert_eq!(text.to_string(), text.to_plain());
+        };
+    }
+    check!("#error<failed>: #path<{}> #r!;_k<{:?}>", name, 1.5);
+    check!("#error;warn<x> #code(fallback='<', fallback-end='>')<{name}>");
+    check!("#c(link='https://a.example')<#b(link='https://b.example')<b> a> #secret<{}>!", name);
+    check!(#[sanitize] "#i<{}>", name);
+}
+
+#[test]
+fn from_ansi() {
+    let text = StyledText::from_ansi(
+        "a\x1b[38;5;9;48;2;0;0;80mb\x1b[1;21;4mc\x1b[m\x1b[2Kd\x1b]8;;https://x.example\x07e"
+    );
+    let spans = text.spans().iter().map(|span| (span.text.as_str(), span.style.to_string())).collect::<Vec<_>>();
+    assert_eq!(spans, [
+        ("a", "".to_owned()),
+        ("b", "bright-red;bg:rgb(0,0,80)".to_owned()),
+        ("c", "bold;underline;bright-red;bg:rgb(0,0,80)".to_owned()),
+        ("d", "".to_owned()),
+        ("e", "".to_owned()),
+    ]);
+    assert_eq!(text.spans()[4].link.as_deref(), Some("https://x.example"));
+    assert_eq!(text.to_plain(), "abcde");
+    // unterminated sequences are dropped
+    assert_eq!(StyledText::from_ansi("x\x1b[31").to_plain(), "x");
+    assert_eq!(StyledText::from_ansi("x\x1b").to_plain(), "x");
+    // other escape sequences are skipped, however many there are
+    assert_eq!(StyledText::from_ansi(&format!("{}x", "\x1b7".repeat(1_000_000))).to_plain(), "x");
+}
+
+#[test]
+fn ansi_round_trip() {
+    let _lock = support::lock();
+    config::set_override(true);
+    macro_rules! check {
+        ($($fmt: tt)*) => {
+            let ansi = cformat!($($fmt)*);
+            let text = StyledText::from_ansi(&ansi);
+            assert_eq!(text, cformat_styled!($($fmt)*));
+            assert_eq!(text.to_ansi(), ansi);
+        };
+    }
+    check!("uncolored, #r<red#g;u<green and underlined>,red again>, uncolored");
+    check!("#s;i;strike<{}> #rgb(1,2,3);_y!<{:>4}>", "x", 1);
+    check!("#f;blink;reverse;conceal<a> #_rgb(200,0,0)<b>");
+    check!("#u(link='https://a.example')<a #i(link='https://b.example')<b>> c");
+}
@@ -0,0 +1,11 @@
+//! Shared by the test files that change the global configuration.
+
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// The override, the theme and the environment are global, tests that change or depend on them hold this lock
+/// so that they do not run concurrently.
+pub fn lock() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    // a failed test must not fail the others too
+    LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
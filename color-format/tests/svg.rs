@@ -0,0 +1,33 @@
+use color_format::{svg::*, *};
+
+#[test]
+fn svg() {
+    let options = SvgOptions { columns: 10, frame: false, ..SvgOptions::default() };
+    let svg = to_svg(&cformat_styled!("#s;r<a&b> #_b<{}>\n#u(link='https://x.example')<l>", "  "), &options);
+    assert_eq!(svg, "\
+<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"108.0\" height=\"63.2\" viewBox=\"0 0 108.0 63.2\">
+<rect width=\"100%\" height=\"100%\" rx=\"0\" fill=\"#000000\"/>
+<g font-family=\"ui-monospace, SFMono-Regular, Menlo, Consolas, monospace\" font-size=\"14\" xml:space=\"preserve\">
+<rect x=\"45.6\" y=\"12.0\" width=\"16.8\" height=\"19.6\" fill=\"#0000ee\"/>
+<text y=\"26.7\"><tspan x=\"12.0\" fill=\"#cd0000\" font-weight=\"bold\">a&amp;b</tspan></text>
+<text y=\"46.3\"><a href=\"https://x.example\"><tspan x=\"12.0\" fill=\"#e5e5e5\" text-decoration=\"underline\">l</tspan></a></text>
+</g>
+</svg>
+");
+    // long lines wrap, secrets are masked and the frame has a title
+    let options = SvgOptions { columns: 4, title: Some("<demo>".to_owned()), ..SvgOptions::default() };
+    let svg = to_svg("abcdef", &options);
+    assert!(svg.contains(">abcd</tspan>") && svg.contains(">ef</tspan>"));
+    assert!(svg.contains("<circle ") && svg.contains(">&lt;demo&gt;</text>"));
+    let svg = to_svg(&cformat_styled!("#secret<{}>", "hunter2"), &SvgOptions { columns: 10, ..options });
+    assert!(svg.contains(">********</tspan>") && !svg.contains("hunter2"));
+    // reversed text gets the default foreground as background
+    let svg = to_svg("\x1b[7mx\x1b[0m", &SvgOptions::default());
+    assert!(svg.contains("fill=\"#e5e5e5\"/>") && svg.contains("fill=\"#000000\">x</tspan>"));
+    // only safe links are clickable
+    let svg = to_svg("\x1b]8;;javascript:alert(1)\x1b\\x\x1b]8;;\x1b\\", &SvgOptions::default());
+    assert!(svg.contains(">x</tspan>") && !svg.contains("<a ") && !svg.contains("javascript"));
+    // a screen needs at least one column
+    let svg = to_svg("ab", &SvgOptions { columns: 0, ..SvgOptions::default() });
+    assert!(svg.contains(">a</tspan>") && svg.contains(">b</tspan>"));
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use color_format::*;
+
+mod support;
+
+#[test]
+fn render() {
+    let _lock = support::lock();
+    config::set_override(true);
+    let template = Template::parse("#r<red#g;u<{a} and underlined>,red again>, {{uncolored}} #_rgb(0,0,80)<{b}>").unwrap();
+    assert_eq!(template.placeholders().collect::<Vec<_>>(), ["a", "b"]);
+    let values: HashMap<_, _> = [("a", "green"), ("b", "blue")].into_iter().collect();
+    assert_eq!(
+        template.render_with(&values, true).unwrap(),
+        cformat!("#r<red#g;u<{} and underlined>,red again>, {{uncolored}} #_rgb(0,0,80)<{}>", "green", "blue")
+    );
+    assert_eq!(
+        template.render_with(&|name: &str| (name == "a").then_some(1), false),
+        Err(RenderError::MissingValue { name: "b".to_owned(), offset: 71 })
+    );
+    assert_eq!(
+        template.render_with(&|name: &str| Some(name.len()), false).unwrap(),
+        "red1 and underlined,red again, {uncolored} 1"
+    );
+    assert_eq!(Template::parse("#> ## #< #s<>").unwrap().render_with(&HashMap::<&str, &str>::new(), true).unwrap(), "> # < ");
+}
+
+#[test]
+fn errors() {
+    fn error(s: &str) -> (TemplateErrorKind, std::ops::Range<usize>) {
+        let e = Template::parse(s).unwrap_err();
+        (e.kind, e.span)
+    }
+    assert_eq!(error("ok #r;bold;nope<x>"), (TemplateErrorKind::UnknownTag("nope".to_owned()), 11..15));
+    assert_eq!(error("#bright-rgb(1,2,3)<x>"), (TemplateErrorKind::InvalidBright("rgb(1,2,3)".to_owned()), 1..18));
+    assert_eq!(error("#_bold<x>"), (TemplateErrorKind::InvalidBackground("bold".to_owned()), 1..6));
+    assert!(matches!(error("#rgb(1,2)<x>").0, TemplateErrorKind::InvalidRgb(_)));
+    assert_eq!(error("a > b"), (TemplateErrorKind::UnmatchedClose, 2..3));
+    assert_eq!(error("#r<a #g<b>"), (TemplateErrorKind::Unclosed, 0..1));
+    assert_eq!(error("a {b"), (TemplateErrorKind::UnclosedPlaceholder, 2..4));
+    assert_eq!(error("a } b"), (TemplateErrorKind::UnmatchedBrace, 2..3));
+    assert_eq!(error("#r x<"), (TemplateErrorKind::MissingOpen, 0..2));
+    assert_eq!(error("x #"), (TemplateErrorKind::MissingTag, 2..3));
+    assert_eq!(error("{a b}"), (TemplateErrorKind::InvalidPlaceholder("a b".to_owned()), 0..5));
+    assert_eq!(
+        Template::parse("#r<x> #blue;z<y>").unwrap_err().to_string(),
+        "unknown tag 'z' at byte 12"
+    );
+}
+
+#[test]
+fn links() {
+    let template = Template::parse("#u(link='https://a.example')<{x} #i(link='https://b.example')<b>> c").unwrap();
+    let values = HashMap::from([("x", "a")]);
+    assert_eq!(template.render_with(&values, false).unwrap(), "a b c");
+    assert_eq!(
+        template.render_with(&values, true).unwrap(),
+        "\x1b[4m\x1b]8;;https://a.example\x1b\\a \x1b[3m\x1b]8;;https://b.example\x1b\\b\x1b[0m\x1b]8;;\x1b\\ c",
+    );
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use color_format::{testing::*, *};
+
+mod support;
+
+#[test]
+fn tags() {
+    assert_eq!(to_tags("plain"), "plain");
+    assert_eq!(to_tags(""), "");
+    assert_eq!(to_tags("\x1b[31mx\x1b[0m\x1b[31my"), "#r<xy>");
+    assert_eq!(to_tags("\x1b[1m\x1b[3ma\x1b[23mb\x1b[22;2mc"), "#s<#i<a>b>#f<c>");
+    assert_eq!(to_tags("\x1b[38;2;1;2;3;48;5;12mrgb\x1b[39mbg"), "#_b!<#rgb(1,2,3)<rgb>bg>");
+    assert_eq!(to_tags("\x1b[33m#<{x}>"), "#y<##<{{x}}#>>");
+    assert_eq!(
+        to_tags("\x1b]8;;https://a.example\x1b\\a\x1b[4mb\x1b]8;;https://b.example\x07c\x1b]8;;\x1b\\d"),
+        "#(link='https://a.example')<a#u<b>>#u<#(link='https://b.example')<c>d>"
+    );
+}
+
+#[test]
+fn tags_of_macros() {
+    let _lock = support::lock();
+    config::set_override(true);
+    assert_eq!(
+        to_tags(&cformat!("uncolored, #r<red#g;u<green and underlined>,red again>, uncolored")),
+        "uncolored, #r<red#u;g<green and underlined>,red again>, uncolored"
+    );
+    assert_eq!(to_tags(&cformat!("#r<r #g<'{:#3}'> r>", "ab")), "#r<r #g<'ab '> r>");
+    // the tags are a template for the same output
+    for template in ["#s;u<a#r<b>#i<c>>d", "#_w<#k<x> y>", "#r(link='https://a.example')<a#g<b>>"] {
+        let ansi = Template::parse(template).unwrap().render_with(&HashMap::<&str, &str>::new(), true).unwrap();
+        let tags = to_tags(&ansi);
+        let rendered = Template::parse(&tags).unwrap().render_with(&HashMap::<&str, &str>::new(), true).unwrap();
+        assert_eq!(to_tags(&rendered), tags);
+    }
+}
+
+#[test]
+fn styled_eq() {
+    let _lock = support::lock();
+    config::set_override(true);
+    let x = 1;
+    assert_styled_eq!("\x1b[1;31merror\x1b[0m: 1", "#r;s<error>: {}", x);
+    assert_styled_eq!("\x1b[31m\x1b[1merror\x1b[22m\x1b[39m\x1b[0m: 1", "#r<#s<error>>: {x}");
+    assert_styled_eq!(String::from("a#b"), "a##b");
+    // plain output when colors are off
+    config::set_override(false);
+    assert_styled_eq!(cformat!("#r;s<error>: {}", x), "#r;s<error>: {}", x);
+    assert_styled_eq!("[!] failed", "#error<failed>");
+}
+
+#[test]
+#[should_panic(expected = "  actual: #r<error>: 1\nexpected: #s;r<error>: 1\n  actual plain: \"error: 1\"")]
+fn styled_ne() {
+    let _lock = support::lock();
+    config::set_override(true);
+    assert_styled_eq!("\x1b[31merror\x1b[0m: 1", "#r;s<error>: {}", 1);
+}
+
+#[test]
+fn screen() {
+    let mut screen = Screen::new(12);
+    screen.write_str("\x1b[32mloading\x1b[0m 10%\r\x1b[32mloading\x1b[0m 99%\r\x1b[2K\x1b[1;31mfailed\x1b[0m\n");
+    screen.write_str("0123456789ABwrapped\n\ttab");
+    assert_eq!(screen.to_plain(), "failed\n0123456789AB\nwrapped\n        tab");
+    assert_eq!(screen.to_tags(), "#s;r<failed>\n0123456789AB\nwrapped\n        tab");
+    assert_eq!(screen.cursor(), (3, 11));
+    assert_eq!(screen.cell(0, 5).unwrap().style, "s;r".parse().unwrap());
+    assert_eq!(screen.cell(0, 6).unwrap(), Cell::default());
+    assert_eq!(screen.cell(0, 12), None);
+    assert_eq!(screen.cell(4, 0), None);
+
+    // erasing and moving in a line
+    let mut screen = Screen::new(20);
+    screen.write_str("abcdef\x1b[3D\x1b[K\x1b[4mxy\x1b[0m\x1b[1G\x1b[2C\x1b[1K|\x1b[5Cz");
+    assert_eq!(screen.to_tags(), "  |#u<xy>   z");
+    screen.write_str("\n\n\x1b[2A\x1b[1Cq");
+    assert_eq!(screen.row_text(0).to_plain(), " q|xy   z");
+    assert_eq!(screen.height(), 3);
+}
+
+#[test]
+fn screen_links_and_writes() {
+    use std::io::Write;
+    let mut screen = Screen::new(40);
+    // escape sequences and characters can be cut off between writes
+    let output = "\x1b]8;;https://a.example\x1b\\ä\x1b]8;;\x1b\\ \x1b[38;5;1mred".as_bytes();
+    for chunk in output.chunks(3) {
+        screen.write_all(chunk).unwrap();
+    }
+    assert_eq!(screen.to_tags(), "#(link='https://a.example')<ä> #r<red>");
+    assert_eq!(screen.cell(0, 0).unwrap().link.as_deref(), Some("https://a.example"));
+    cwrite!(screen, "\n#g<ok>").unwrap();
+    assert_eq!(screen.to_plain(), "ä red\nok");
+}
+
+#[test]
+#[should_panic(expected = "a screen needs at least one column")]
+fn screen_without_columns() {
+    Screen::new(0);
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use color_format::*;
+
+mod support;
+
+/// Without an installed theme the one for the detected background is used.
+fn dark_background() {
+    std::env::set_var(config::BACKGROUND_VAR, "dark");
+    config::refresh();
+}
+
+#[test]
+fn default_theme() {
+    let _lock = support::lock();
+    config::set_override(true);
+    dark_background();
+    config::unset_theme();
+    assert_eq!(config::theme(), Theme::dark());
+    assert_eq!(cformat!("#error<failed>: {}", 1), cformat!("#s;r!<failed>: {}", 1));
+    assert_eq!(cformat!("#path<{}> #code<x>", "a.rs"), cformat!("#u;c!<{}> #m!<x>", "a.rs"));
+    // only the attributes set by the theme replace the outer ones
+    assert_eq!(cformat!("#_k;i<a #error<b> c>"), cformat!("#_k;i<a #s;r!<b> c>"));
+    assert_eq!(cformat!("#warn<#y<{}>>", "w"), cformat!("#s;y<{}>", "w"));
+}
+
+#[test]
+fn switch_theme() {
+    let _lock = support::lock();
+    config::set_override(true);
+    dark_background();
+    config::set_theme(Theme::light());
+    assert_eq!(cformat!("#error<failed>: {}", 1), cformat!("#s;r<failed>: {}", 1));
+    let mut theme = Theme::high_contrast();
+    theme.set(Semantic::Path, "green".parse().unwrap());
+    config::set_theme(theme);
+    assert_eq!(cformat!("#path<{}>", "a.rs"), cformat!("#g<{}>", "a.rs"));
+    let template = Template::parse("#warn<{x}>").unwrap();
+    let values = HashMap::from([("x", 1)]);
+    assert_eq!(template.render(&values).unwrap(), cformat!("#s;k;_y!<1>"));
+    config::unset_theme();
+    assert_eq!(cformat!("#error<failed>"), cformat!("#s;r!<failed>"));
+    assert_eq!(template.render_with(&values, false).unwrap(), "[warning] 1");
+}
+
+#[test]
+fn names() {
+    assert_eq!(Semantic::from_name("warn"), Some(Semantic::Warn));
+    assert_eq!(Semantic::from_name("bold"), None);
+    assert!(Semantic::ALL.iter().all(|s| Semantic::from_name(s.name()) == Some(*s)));
+    assert_eq!(Theme::dark().get(Semantic::Code), "bright-magenta".parse().unwrap());
+}
@@ -0,0 +1,59 @@
+use std::{fmt::Write as _, io::Write as _};
+
+use color_format::*;
+
+mod support;
+
+#[test]
+fn writer_decision() {
+    let _lock = support::lock();
+    config::set_override(false);
+
+    let mut colored = ColorWriter::new(Vec::new(), true);
+    cwrite!(colored, "#r<red>").unwrap();
+    cwriteln!(&mut colored, " #g<{}>", "green").unwrap();
+    assert_eq!(colored.into_inner(), b"\x1b[31mred\x1b[0m \x1b[32mgreen\x1b[0m\n");
+
+    let mut plain = ColorWriter::new(String::new(), false);
+    config::set_override(true);
+    cwrite!(plain, "#r<red>").unwrap();
+    assert_eq!(plain.get_ref(), "red");
+
+    // writers without their own decision use the global configuration
+    let mut s = String::new();
+    cwrite!(s, "#r<red>").unwrap();
+    assert_eq!(s, "\x1b[31mred\x1b[0m");
+
+    // the override also applies to the detection of standard writers
+    let mut buf = Vec::new();
+    cwrite!(buf, "#r<red>").unwrap();
+    assert_eq!(buf, b"\x1b[31mred\x1b[0m");
+
+    // the writer expression is evaluated once, for the decision and the output
+    let mut writers = [ColorWriter::new(String::new(), false), ColorWriter::new(String::new(), true)];
+    let mut next = writers.iter_mut();
+    cwrite!(next.next().unwrap(), "#r<{}>", 1).unwrap();
+    assert!(next.next().is_some());
+    assert_eq!(writers.map(ColorWriter::into_inner), ["1".to_owned(), String::new()]);
+}
+
+struct Status(bool);
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 {
+            cwrite!(f, "#g<ok>")
+        } else {
+            cwrite!(f, "#r<{}>", cformat!("#s<failed>"))
+        }
+    }
+}
+
+#[test]
+fn plain_display() {
+    let _lock = support::lock();
+    config::set_override(true);
+    assert_eq!(Status(true).to_string(), "\x1b[32mok\x1b[0m");
+    assert_eq!(plain(&Status(true)).to_string(), "ok");
+    // nested macros are plain too
+    assert_eq!(format!("{} {}", plain(&Status(false)), Status(true)), "failed \x1b[32mok\x1b[0m");
+}